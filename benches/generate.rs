@@ -0,0 +1,121 @@
+use criterion::{black_box, criterion_group, criterion_main, Bencher, Criterion};
+use frostbit::{
+    unix_millis, ConstSnowflakeGenerator, OverflowPolicy, ShardedSnowflakeGenerator,
+    SnowflakeConfig, SnowflakeGenerator, SnowflakeGeneratorError, TimestampSource,
+};
+
+const BATCH_SIZE: usize = 10_000;
+const THREADS: usize = 4;
+
+/// A config with a wide enough sequence that 10,000 snowflakes fit in one millisecond.
+fn bench_config() -> SnowflakeConfig {
+    SnowflakeConfig::new(41, 6, 16).unwrap()
+}
+
+type UnixMillis = fn() -> Result<u64, &'static str>;
+
+/// Generate `BATCH_SIZE` snowflakes with `generate` on a fresh runtime-config generator.
+fn bench_runtime_generator(
+    b: &mut Bencher,
+    generate: impl Fn(&SnowflakeGenerator<UnixMillis>) -> Result<u64, SnowflakeGeneratorError>,
+) {
+    b.iter_batched(
+        || {
+            SnowflakeGenerator::new_with_config(0, 0, unix_millis as UnixMillis, bench_config())
+                .unwrap()
+        },
+        |generator| {
+            for _ in 0..BATCH_SIZE {
+                black_box(generate(&generator).unwrap());
+            }
+        },
+        criterion::BatchSize::SmallInput,
+    )
+}
+
+/// Read the clock `BATCH_SIZE` times, without generating anything.
+fn read_clock(b: &mut Bencher, source: impl TimestampSource) {
+    b.iter(|| {
+        for _ in 0..BATCH_SIZE {
+            black_box(source.timestamp().unwrap());
+        }
+    })
+}
+
+fn timestamp_source(c: &mut Criterion) {
+    let mut group = c.benchmark_group("timestamp x10000 by source");
+    group.bench_function("unix_millis", |b| read_clock(b, unix_millis));
+    group.bench_function("fixed closure", |b| {
+        read_clock(b, || Ok::<_, &'static str>(black_box(0x1234)))
+    });
+    group.finish();
+}
+
+fn generate_const(c: &mut Criterion) {
+    let mut group = c.benchmark_group("generate x10000 by config");
+    group.bench_function("runtime config", |b| {
+        bench_runtime_generator(b, SnowflakeGenerator::generate)
+    });
+    group.bench_function("runtime config, unchecked", |b| {
+        bench_runtime_generator(b, SnowflakeGenerator::generate_unchecked)
+    });
+    group.bench_function("const generic", |b| {
+        b.iter_batched(
+            || ConstSnowflakeGenerator::<41, 6, 16, _>::new(0, 0, unix_millis).unwrap(),
+            |generator| {
+                for _ in 0..BATCH_SIZE {
+                    black_box(generator.generate().unwrap());
+                }
+            },
+            criterion::BatchSize::SmallInput,
+        )
+    });
+    group.finish();
+}
+
+fn generate_batch(c: &mut Criterion) {
+    c.bench_function("generate_batch 10000", |b| {
+        b.iter_batched(
+            || SnowflakeGenerator::new_with_config(0, 0, unix_millis, bench_config()).unwrap(),
+            |generator| black_box(generator.generate_batch(BATCH_SIZE).unwrap()),
+            criterion::BatchSize::SmallInput,
+        )
+    });
+}
+
+/// Generate `BATCH_SIZE` snowflakes split across `THREADS` threads.
+fn generate_contended(generate: impl Fn() -> u64 + Sync) {
+    std::thread::scope(|scope| {
+        for _ in 0..THREADS {
+            scope.spawn(|| {
+                for _ in 0..BATCH_SIZE / THREADS {
+                    black_box(generate());
+                }
+            });
+        }
+    });
+}
+
+fn generate_threaded(c: &mut Criterion) {
+    let config = bench_config().with_overflow_policy(OverflowPolicy::SpinWait);
+    let mut group = c.benchmark_group("contended generate x10000");
+    group.bench_function("single atomic", |b| {
+        let generator = SnowflakeGenerator::new_with_config(0, 0, unix_millis, config).unwrap();
+        b.iter(|| generate_contended(|| generator.generate().unwrap()))
+    });
+    group.bench_function("sharded", |b| {
+        let generator =
+            ShardedSnowflakeGenerator::new_with_config(0, 0, unix_millis, config, 2).unwrap();
+        b.iter(|| generate_contended(|| generator.generate().unwrap()))
+    });
+    group.finish();
+}
+
+criterion_group!(
+    benches,
+    timestamp_source,
+    generate_const,
+    generate_batch,
+    generate_threaded
+);
+criterion_main!(benches);
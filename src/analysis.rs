@@ -0,0 +1,283 @@
+//! Helpers for analyzing collections of already generated snowflakes.
+
+#[cfg(feature = "alloc")]
+use alloc::{collections::BTreeMap, vec::Vec};
+use core::cmp::Ordering;
+
+use crate::{splitmix64, SnowflakeConfig};
+
+/// Count the snowflakes that were generated at or after `since_ms`.
+///
+/// `since_ms` is an absolute timestamp, so the epoch the snowflakes were
+/// generated with is added back to each decoded timestamp before comparing.
+pub fn count_after(ids: &[u64], since_ms: u64, epoch: u64, config: &SnowflakeConfig) -> usize {
+    ids.iter()
+        .filter(|id| config.timestamp_of(**id) + epoch >= since_ms)
+        .count()
+}
+
+/// The number of IDs that could have been issued between two snowflakes.
+///
+/// The snowflakes are compared by their timestamp and sequence, ignoring the
+/// machine ID, so this approximates how many IDs a single machine could have
+/// generated between the two. The result is negative if `b` comes before `a`.
+pub fn id_distance(a: u64, b: u64, config: &SnowflakeConfig) -> i64 {
+    ordering_key(b, config) as i64 - ordering_key(a, config) as i64
+}
+
+/// Check whether two snowflakes were generated in the same millisecond.
+///
+/// Only the timestamps are compared, so snowflakes from different machines
+/// count as the same millisecond too. With a [crate::TimeUnit] other than
+/// milliseconds this compares whole ticks of that unit instead.
+pub fn same_millisecond(a: u64, b: u64, config: &SnowflakeConfig) -> bool {
+    config.timestamp_of(a) == config.timestamp_of(b)
+}
+
+/// Compare two snowflakes by their timestamps alone.
+///
+/// Snowflakes from the same millisecond compare as equal, whatever their machine
+/// IDs and sequences, so a stable sort with this keeps their original order.
+pub fn cmp_by_timestamp(a: u64, b: u64, config: &SnowflakeConfig) -> Ordering {
+    config.timestamp_of(a).cmp(&config.timestamp_of(b))
+}
+
+fn ordering_key(snowflake: u64, config: &SnowflakeConfig) -> u64 {
+    config.timestamp_of(snowflake) << config.sequence_bits | config.sequence_of(snowflake)
+}
+
+/// Estimate the creation time of a snowflake without knowing its exact config.
+///
+/// Only the position of the timestamp is needed: `low_bits` is the number of bits
+/// below it, e.g. 22 for Twitter-like snowflakes. Any bits above the timestamp
+/// are assumed to be unset.
+pub fn approx_timestamp(id: u64, low_bits: u64, epoch: u64) -> u64 {
+    (id >> low_bits) + epoch
+}
+
+/// Decode snowflakes into separate columns of timestamps, machine IDs, and sequences.
+///
+/// This is equivalent to decoding each snowflake on its own, but the struct of
+/// arrays layout is friendlier to columnar processing of large batches.
+#[cfg(feature = "alloc")]
+pub fn decode_columnar(ids: &[u64], config: &SnowflakeConfig) -> (Vec<u64>, Vec<u64>, Vec<u64>) {
+    let timestamps = ids.iter().map(|id| config.timestamp_of(*id)).collect();
+    let machine_ids = ids.iter().map(|id| config.machine_id_of(*id)).collect();
+    let sequences = ids.iter().map(|id| config.sequence_of(*id)).collect();
+
+    (timestamps, machine_ids, sequences)
+}
+
+/// Count how many snowflakes fall into each millisecond.
+///
+/// The map is keyed by the epoch-relative timestamp of the snowflakes. Buckets
+/// with counts close to the sequence max show where generation came close to
+/// overflowing.
+#[cfg(feature = "alloc")]
+pub fn density_per_ms(ids: &[u64], config: &SnowflakeConfig) -> BTreeMap<u64, u32> {
+    let mut density = BTreeMap::new();
+    for id in ids {
+        *density.entry(config.timestamp_of(*id)).or_insert(0) += 1;
+    }
+
+    density
+}
+
+/// Find machine IDs that appear to be shared by more than one generator.
+///
+/// A single generator can issue at most `sequence_max + 1` snowflakes per
+/// millisecond, so a machine ID with more than that in any one millisecond must
+/// have been assigned to several generators at once. The returned machine IDs are
+/// sorted. This is only a heuristic: reuse goes unnoticed when the generators
+/// involved are never busy enough to exceed the limit together.
+#[cfg(feature = "alloc")]
+pub fn detect_machine_id_reuse(ids: &[u64], config: &SnowflakeConfig) -> Vec<u64> {
+    let mut counts = BTreeMap::new();
+    for id in ids {
+        let key = (config.machine_id_of(*id), config.timestamp_of(*id));
+        *counts.entry(key).or_insert(0u64) += 1;
+    }
+
+    let mut reused: Vec<u64> = counts
+        .into_iter()
+        .filter(|(_, count)| *count > config.sequence_max + 1)
+        .map(|((machine_id, _), _)| machine_id)
+        .collect();
+    reused.dedup();
+    reused
+}
+
+/// The partition out of `partitions` that a snowflake is routed to.
+///
+/// This is simply `id % partitions`. The sequence sits in the low bits of the
+/// snowflake, so when traffic is bursty (e.g. most milliseconds only see one or two
+/// IDs) the low partitions get far more than their share. Prefer
+/// [partition_of_hashed] unless the IDs are known to be spread evenly.
+///
+/// Panics if `partitions` is 0.
+pub fn partition_of(id: u64, partitions: u64) -> u64 {
+    id % partitions
+}
+
+/// The partition out of `partitions` that a snowflake is routed to, by hash.
+///
+/// The snowflake is mixed with the splitmix64 finalizer before being reduced, so
+/// every bit of it affects the partition and the split is uniform regardless of
+/// traffic patterns. The mapping is stable across processes and releases.
+///
+/// Panics if `partitions` is 0.
+pub fn partition_of_hashed(id: u64, partitions: u64) -> u64 {
+    splitmix64(id) % partitions
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::atomic::{AtomicU64, Ordering};
+
+    use super::*;
+    use crate::SnowflakeGenerator;
+
+    #[test]
+    fn test_count_after() {
+        const EPOCH: u64 = 1000;
+        let clock = AtomicU64::new(EPOCH + 10);
+        let generator =
+            SnowflakeGenerator::new(0x10, EPOCH, || Ok(clock.load(Ordering::SeqCst))).unwrap();
+
+        let mut ids = Vec::new();
+        for timestamp in EPOCH + 10..EPOCH + 20 {
+            clock.store(timestamp, Ordering::SeqCst);
+            ids.push(generator.generate().unwrap());
+            ids.push(generator.generate().unwrap());
+        }
+
+        let config = SnowflakeConfig::default();
+        assert_eq!(count_after(&ids, EPOCH + 15, EPOCH, &config), 10);
+        assert_eq!(count_after(&ids, 0, EPOCH, &config), 20);
+        assert_eq!(count_after(&ids, EPOCH + 20, EPOCH, &config), 0);
+        assert_eq!(count_after(&[], 0, EPOCH, &config), 0);
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn test_decode_columnar() {
+        let config = SnowflakeConfig::new(42, 5, 8).unwrap();
+        let ids = (0..100u64)
+            .map(|i| config.pack(i * 7, i % 32, i * 3))
+            .collect::<Vec<_>>();
+
+        let (timestamps, machine_ids, sequences) = decode_columnar(&ids, &config);
+        assert_eq!(timestamps.len(), ids.len());
+        for (i, id) in ids.iter().enumerate() {
+            assert_eq!(timestamps[i], config.timestamp_of(*id));
+            assert_eq!(machine_ids[i], config.machine_id_of(*id));
+            assert_eq!(sequences[i], config.sequence_of(*id));
+        }
+    }
+
+    #[test]
+    fn test_compare_by_timestamp() {
+        let config = SnowflakeConfig::default();
+        let a = config.pack(0x1234, 0x3FF, 5);
+        let b = config.pack(0x1234, 0x10, 9);
+        let c = config.pack(0x1235, 0, 0);
+
+        assert!(same_millisecond(a, b, &config));
+        assert!(!same_millisecond(b, c, &config));
+        assert!(cmp_by_timestamp(a, b, &config).is_eq());
+        assert!(cmp_by_timestamp(a, c, &config).is_lt());
+        assert!(cmp_by_timestamp(c, b, &config).is_gt());
+
+        let mut ids = [c, a, b];
+        ids.sort_by(|x, y| cmp_by_timestamp(*x, *y, &config));
+        assert_eq!(ids, [a, b, c]);
+    }
+
+    #[test]
+    fn test_approx_timestamp() {
+        let config = SnowflakeConfig::default();
+        let snowflake = config.pack(0x1234, 0x3FF, 0xFFF);
+        assert_eq!(approx_timestamp(snowflake, 22, 1000), 0x1234 + 1000);
+    }
+
+    #[test]
+    fn test_id_distance() {
+        let config = SnowflakeConfig::default();
+        let a = config.pack(0x1234, 0x10, 5);
+        let b = config.pack(0x1236, 0x3FF, 2);
+
+        let expected = 2 * (config.sequence_max as i64 + 1) - 3;
+        assert_eq!(id_distance(a, b, &config), expected);
+        assert_eq!(id_distance(b, a, &config), -expected);
+        assert_eq!(id_distance(a, a, &config), 0);
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn test_density_per_ms() {
+        let config = SnowflakeConfig::default();
+        let ids = [
+            config.pack(0x1234, 0x10, 0),
+            config.pack(0x1234, 0x10, 1),
+            config.pack(0x1234, 0x11, 0),
+            config.pack(0x1236, 0x10, 0),
+        ];
+
+        let density = density_per_ms(&ids, &config);
+        assert_eq!(
+            density.into_iter().collect::<Vec<_>>(),
+            vec![(0x1234, 3), (0x1236, 1)]
+        );
+        assert!(density_per_ms(&[], &config).is_empty());
+    }
+
+    #[test]
+    fn test_partition_distribution() {
+        // one ID per millisecond, so every sequence is 0
+        let config = SnowflakeConfig::default();
+        let ids = (0..10_000u64)
+            .map(|i| config.pack(i, 0x10, 0))
+            .collect::<Vec<_>>();
+
+        let mut modulo = [0; 8];
+        let mut hashed = [0; 8];
+        for id in &ids {
+            modulo[partition_of(*id, 8) as usize] += 1;
+            hashed[partition_of_hashed(*id, 8) as usize] += 1;
+        }
+
+        assert_eq!(modulo[0], ids.len());
+        for count in hashed {
+            assert!((1_100..1_400).contains(&count), "{hashed:?}");
+        }
+    }
+
+    #[test]
+    fn test_partition_is_stable() {
+        assert_eq!(partition_of(0x48d010235, 10), 0x48d010235 % 10);
+        assert_eq!(
+            partition_of_hashed(0x48d010235, 16),
+            partition_of_hashed(0x48d010235, 16)
+        );
+        assert!(partition_of_hashed(0x48d010235, 3) < 3);
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn test_detect_machine_id_reuse() {
+        let config = SnowflakeConfig::new(42, 5, 2).unwrap();
+        let mut ids = Vec::new();
+        // machine 1 is at the limit, machines 2 and 3 go over it in one millisecond
+        for sequence in 0..4 {
+            ids.push(config.pack(0x1234, 1, sequence));
+            ids.push(config.pack(0x1234, 2, sequence));
+            ids.push(config.pack(0x1235, 3, sequence));
+        }
+        ids.push(config.pack(0x1234, 2, 0));
+        ids.push(config.pack(0x1235, 3, 1));
+        ids.push(config.pack(0x1236, 1, 0));
+
+        assert_eq!(detect_machine_id_reuse(&ids, &config), vec![2, 3]);
+        assert!(detect_machine_id_reuse(&ids[..12], &config).is_empty());
+    }
+}
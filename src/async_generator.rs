@@ -0,0 +1,169 @@
+//! Snowflakes generated from an async timestamp source.
+//!
+//! Only reading the timestamp is awaited. Sequence IDs are handed out by the same
+//! lock-free [TimestampSequenceGenerator] as in [crate::SnowflakeGenerator], so
+//! generating never holds anything across an await point.
+
+use core::future::Future;
+
+use crate::timestamp_sequence::TimestampSequenceGenerator;
+use crate::{OverflowPolicy, SnowflakeConfig, SnowflakeGeneratorError};
+
+/// A generator whose timestamp function returns a future.
+///
+/// This suits clocks that have to be queried over the network, like a clock-sync
+/// service. The timestamp function may fail with any error that converts into a
+/// [SnowflakeGeneratorError], e.g. a plain `&'static str`.
+///
+/// ```rust
+/// use frostbit::AsyncSnowflakeGenerator;
+///
+/// # async fn example() {
+/// let gen = AsyncSnowflakeGenerator::new(0, 0, || async { Ok::<_, &str>(0x1234) })
+///     .await
+///     .unwrap();
+/// let snowflake = gen.generate().await.unwrap();
+/// # }
+/// ```
+pub struct AsyncSnowflakeGenerator<F> {
+    machine_id: u32,
+    ts_gen: TimestampSequenceGenerator,
+    epoch: u64,
+    get_timestamp: F,
+    config: SnowflakeConfig,
+}
+
+impl<F, Fut, E> AsyncSnowflakeGenerator<F>
+where
+    F: Fn() -> Fut,
+    Fut: Future<Output = Result<u64, E>>,
+    E: Into<SnowflakeGeneratorError>,
+{
+    /// Create a new AsyncSnowflakeGenerator with default configuration.
+    ///
+    /// The timestamp function is awaited once to seed the sequence state.
+    pub async fn new(
+        machine_id: u32,
+        epoch: u64,
+        get_timestamp: F,
+    ) -> Result<Self, SnowflakeGeneratorError> {
+        Self::new_with_config(machine_id, epoch, get_timestamp, SnowflakeConfig::default()).await
+    }
+
+    /// Create a new AsyncSnowflakeGenerator with a custom configuration.
+    ///
+    /// The machine ID is checked against the config the same way
+    /// [crate::SnowflakeGenerator::new_with_config] does.
+    pub async fn new_with_config(
+        machine_id: u32,
+        epoch: u64,
+        get_timestamp: F,
+        config: SnowflakeConfig,
+    ) -> Result<Self, SnowflakeGeneratorError> {
+        config.machine_id_policy.check(machine_id, &config)?;
+
+        let timestamp = Self::get_epoch_relative_timestamp(&get_timestamp, epoch, &config).await?;
+        Ok(Self {
+            machine_id,
+            ts_gen: TimestampSequenceGenerator::new(timestamp, config),
+            epoch,
+            get_timestamp,
+            config,
+        })
+    }
+
+    /// Generate a new snowflake.
+    ///
+    /// This behaves like [crate::SnowflakeGenerator::generate]. Under
+    /// [OverflowPolicy::SpinWait] the timestamp function is awaited over and over
+    /// until the next millisecond, without yielding in between unless the future
+    /// itself does.
+    pub async fn generate(&self) -> Result<u64, SnowflakeGeneratorError> {
+        loop {
+            let timestamp =
+                Self::get_epoch_relative_timestamp(&self.get_timestamp, self.epoch, &self.config)
+                    .await?;
+            match self.ts_gen.increment_sequence(timestamp) {
+                Ok(timestamp_sequence) => {
+                    return Ok(
+                        timestamp_sequence.into_snowflake(self.machine_id as u64, &self.config)
+                    )
+                }
+                Err(SnowflakeGeneratorError::SequenceOverflow)
+                    if self.config.overflow_policy == OverflowPolicy::SpinWait =>
+                {
+                    self.wait_until_after(timestamp).await?
+                }
+                Err(error) => return Err(error),
+            }
+        }
+    }
+
+    async fn wait_until_after(&self, last_timestamp: u64) -> Result<(), SnowflakeGeneratorError> {
+        while Self::get_epoch_relative_timestamp(&self.get_timestamp, self.epoch, &self.config)
+            .await?
+            <= last_timestamp
+        {
+            core::hint::spin_loop();
+        }
+        Ok(())
+    }
+
+    async fn get_epoch_relative_timestamp(
+        get_timestamp: &F,
+        epoch: u64,
+        config: &SnowflakeConfig,
+    ) -> Result<u64, SnowflakeGeneratorError> {
+        let timestamp = get_timestamp().await.map_err(Into::into)?;
+        config.epoch_relative(timestamp, epoch)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::atomic::{AtomicU64, Ordering};
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_async_generate() {
+        let config = SnowflakeConfig::new(41, 10, 2)
+            .unwrap()
+            .with_overflow_policy(OverflowPolicy::SpinWait);
+        let reads = AtomicU64::new(0);
+        // a clock-sync service that yields to the runtime, and advances every 8 reads
+        let clock = || async {
+            tokio::task::yield_now().await;
+            Ok::<_, &str>(0x1234 + reads.fetch_add(1, Ordering::SeqCst) / 8)
+        };
+        let generator = AsyncSnowflakeGenerator::new_with_config(0x10, 0, clock, config)
+            .await
+            .unwrap();
+
+        for sequence in 0..4 {
+            let snowflake = generator.generate().await.unwrap();
+            assert_eq!(snowflake, config.pack(0x1234, 0x10, sequence));
+        }
+        let snowflake = generator.generate().await.unwrap();
+        assert_eq!(snowflake, config.pack(0x1235, 0x10, 0));
+    }
+
+    #[tokio::test]
+    async fn test_async_timestamp_error() {
+        let reads = AtomicU64::new(0);
+        let clock = || async {
+            match reads.fetch_add(1, Ordering::SeqCst) {
+                0 => Ok(0x1234),
+                _ => Err("clock-sync service unavailable"),
+            }
+        };
+        let generator = AsyncSnowflakeGenerator::new(0x10, 0, clock).await.unwrap();
+
+        assert!(matches!(
+            generator.generate().await,
+            Err(SnowflakeGeneratorError::TimestampError(
+                "clock-sync service unavailable"
+            ))
+        ));
+    }
+}
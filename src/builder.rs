@@ -0,0 +1,140 @@
+//! Named-argument construction of a [SnowflakeGenerator].
+
+use crate::{SnowflakeConfig, SnowflakeGenerator, SnowflakeGeneratorError, TimestampSource};
+
+/// A builder for a [SnowflakeGenerator].
+///
+/// The machine ID, epoch, and timestamp function must all be set, the config
+/// defaults to [SnowflakeConfig::default]. Building fails with
+/// [SnowflakeGeneratorError::MissingField] naming the first field that wasn't set.
+///
+/// ```rust
+/// use frostbit::SnowflakeGeneratorBuilder;
+///
+/// let generator = SnowflakeGeneratorBuilder::new()
+///     .machine_id(0x10)
+///     .epoch(0)
+///     .timestamp_fn(|| Ok(0x1234))
+///     .build()
+///     .unwrap();
+/// assert_eq!(generator.generate().unwrap(), 0x48D010000);
+/// ```
+pub struct SnowflakeGeneratorBuilder<T = fn() -> Result<u64, &'static str>> {
+    machine_id: Option<u32>,
+    epoch: Option<u64>,
+    get_timestamp: Option<T>,
+    config: SnowflakeConfig,
+}
+
+impl SnowflakeGeneratorBuilder {
+    /// Create a builder with nothing set.
+    pub fn new() -> Self {
+        Self {
+            machine_id: None,
+            epoch: None,
+            get_timestamp: None,
+            config: SnowflakeConfig::default(),
+        }
+    }
+}
+
+impl Default for SnowflakeGeneratorBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> SnowflakeGeneratorBuilder<T> {
+    /// Set the machine ID of the generator.
+    pub fn machine_id(mut self, machine_id: u32) -> Self {
+        self.machine_id = Some(machine_id);
+        self
+    }
+
+    /// Set the epoch timestamps are counted from, in the units of the timestamp function.
+    pub fn epoch(mut self, epoch: u64) -> Self {
+        self.epoch = Some(epoch);
+        self
+    }
+
+    /// Set the function the generator reads the current timestamp from.
+    pub fn timestamp_fn<C>(self, get_timestamp: C) -> SnowflakeGeneratorBuilder<C>
+    where
+        C: TimestampSource,
+    {
+        SnowflakeGeneratorBuilder {
+            machine_id: self.machine_id,
+            epoch: self.epoch,
+            get_timestamp: Some(get_timestamp),
+            config: self.config,
+        }
+    }
+
+    /// Set the config of the generator.
+    pub fn config(mut self, config: SnowflakeConfig) -> Self {
+        self.config = config;
+        self
+    }
+}
+
+impl<T: TimestampSource> SnowflakeGeneratorBuilder<T> {
+    /// Build the generator.
+    ///
+    /// This is equivalent to calling [SnowflakeGenerator::new_with_config], and fails
+    /// the same way once every field is set.
+    pub fn build(self) -> Result<SnowflakeGenerator<T>, SnowflakeGeneratorError> {
+        let machine_id = self
+            .machine_id
+            .ok_or(SnowflakeGeneratorError::MissingField("machine_id"))?;
+        let epoch = self
+            .epoch
+            .ok_or(SnowflakeGeneratorError::MissingField("epoch"))?;
+        let get_timestamp = self
+            .get_timestamp
+            .ok_or(SnowflakeGeneratorError::MissingField("timestamp_fn"))?;
+
+        SnowflakeGenerator::new_with_config(machine_id, epoch, get_timestamp, self.config)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_build_with_config() {
+        let config = SnowflakeConfig::new(42, 5, 8).unwrap();
+        let generator = SnowflakeGeneratorBuilder::new()
+            .timestamp_fn(|| Ok(0x1234 + 1000))
+            .config(config)
+            .epoch(1000)
+            .machine_id(0x1A)
+            .build()
+            .unwrap();
+
+        let snowflake = generator.generate().unwrap();
+        assert_eq!(config.timestamp_of(snowflake), 0x1234);
+        assert_eq!(config.machine_id_of(snowflake), 0x1A);
+    }
+
+    #[test]
+    fn test_build_missing_field() {
+        let result = SnowflakeGeneratorBuilder::new()
+            .machine_id(0x10)
+            .timestamp_fn(|| Ok(0x1234))
+            .build();
+        assert!(matches!(
+            result,
+            Err(SnowflakeGeneratorError::MissingField("epoch"))
+        ));
+
+        let result = SnowflakeGeneratorBuilder::new()
+            .machine_id(0x10)
+            .epoch(0)
+            .build();
+        assert!(matches!(
+            result,
+            Err(SnowflakeGeneratorError::MissingField("timestamp_fn"))
+        ));
+    }
+}
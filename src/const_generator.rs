@@ -0,0 +1,169 @@
+//! Snowflakes generated with the bit widths fixed at compile time.
+//!
+//! [crate::SnowflakeGenerator] reads its masks and shifts from a [SnowflakeConfig]
+//! on every call. Here they are associated consts of the generator type, so they
+//! fold into the generated code. This only covers the plain Twitter layout of
+//! `[timestamp | machine ID | sequence]` in milliseconds. Anything else needs the
+//! runtime config.
+
+use core::sync::atomic::Ordering;
+
+use crate::sync::AtomicU64;
+use crate::{SnowflakeConfig, SnowflakeGeneratorError, TimestampSource};
+
+/// A generator with `TS` timestamp bits, `MID` machine ID bits, and `SEQ` sequence
+/// bits.
+///
+/// The widths are checked when the generator is built, with the same rules as
+/// [SnowflakeConfig::new], so an invalid combination fails to compile. Snowflakes
+/// are packed exactly as by a [crate::SnowflakeGenerator] with
+/// `SnowflakeConfig::new(TS, MID, SEQ)`, which [ConstSnowflakeGenerator::config]
+/// returns for decoding them. The sequence overflow fails with
+/// [SnowflakeGeneratorError::SequenceOverflow], and a clock that goes backwards
+/// keeps counting on the last timestamp, like the defaults of the runtime config.
+///
+/// ```rust
+/// use frostbit::ConstSnowflakeGenerator;
+///
+/// let gen = ConstSnowflakeGenerator::<41, 10, 12, _>::new(0x10, 0, || Ok(0x1234)).unwrap();
+/// let snowflake = gen.generate().unwrap();
+/// assert_eq!(gen.config().decode(snowflake).machine_id, 0x10);
+/// ```
+pub struct ConstSnowflakeGenerator<const TS: u64, const MID: u64, const SEQ: u64, T>
+where
+    T: TimestampSource,
+{
+    shifted_machine_id: u64,
+    state: AtomicU64,
+    epoch: u64,
+    get_timestamp: T,
+}
+
+impl<const TS: u64, const MID: u64, const SEQ: u64, T> ConstSnowflakeGenerator<TS, MID, SEQ, T>
+where
+    T: TimestampSource,
+{
+    const VALID: () = assert!(
+        TS > 0 && MID > 0 && SEQ > 0 && TS + MID + SEQ <= 64,
+        "invalid snowflake bit widths"
+    );
+
+    /// The largest timestamp is reserved, as for [SnowflakeConfig::timestamp_max].
+    pub const TIMESTAMP_MAX: u64 = (1 << TS) - 1;
+    pub const MACHINE_ID_MAX: u64 = (1 << MID) - 1;
+    pub const SEQUENCE_MAX: u64 = (1 << SEQ) - 1;
+    pub const MACHINE_ID_SHIFT: u64 = SEQ;
+    pub const TIMESTAMP_SHIFT: u64 = SEQ + MID;
+
+    const CONFIG: SnowflakeConfig = SnowflakeConfig::new_unchecked(TS, MID, SEQ);
+
+    /// Create a new ConstSnowflakeGenerator.
+    ///
+    /// Returns [SnowflakeGeneratorError::MachineIdOverflow] if the machine ID doesn't
+    /// fit in `MID` bits.
+    pub fn new(
+        machine_id: u32,
+        epoch: u64,
+        get_timestamp: T,
+    ) -> Result<Self, SnowflakeGeneratorError> {
+        #[allow(clippy::let_unit_value)]
+        let () = Self::VALID;
+        if machine_id as u64 > Self::MACHINE_ID_MAX {
+            return Err(SnowflakeGeneratorError::MachineIdOverflow);
+        }
+
+        let timestamp = Self::get_epoch_relative_timestamp(&get_timestamp, epoch)?;
+        Ok(Self {
+            shifted_machine_id: (machine_id as u64) << Self::MACHINE_ID_SHIFT,
+            state: AtomicU64::new(timestamp << Self::TIMESTAMP_SHIFT),
+            epoch,
+            get_timestamp,
+        })
+    }
+
+    /// The runtime config with the same layout, for decoding snowflakes.
+    pub fn config(&self) -> SnowflakeConfig {
+        Self::CONFIG
+    }
+
+    /// Generate a new snowflake.
+    ///
+    /// This behaves like [crate::SnowflakeGenerator::generate] with the default
+    /// policies.
+    pub fn generate(&self) -> Result<u64, SnowflakeGeneratorError> {
+        let timestamp = Self::get_epoch_relative_timestamp(&self.get_timestamp, self.epoch)?;
+        let new_state = timestamp << Self::TIMESTAMP_SHIFT;
+
+        // The state is the snowflake minus the machine ID, with the sequence being the
+        // next one to hand out. A sequence of SEQUENCE_MAX + 1 marks the timestamp as
+        // used up, and is never counted past, so it can't carry into the timestamp.
+        let mut prev_state = self.state.load(Ordering::SeqCst);
+        loop {
+            let state = prev_state.max(new_state);
+            if state & ((1 << Self::TIMESTAMP_SHIFT) - 1) > Self::SEQUENCE_MAX {
+                return Err(SnowflakeGeneratorError::SequenceOverflow);
+            }
+
+            match self.state.compare_exchange_weak(
+                prev_state,
+                state + 1,
+                Ordering::SeqCst,
+                Ordering::SeqCst,
+            ) {
+                Ok(_) => return Ok(state | self.shifted_machine_id),
+                Err(updated) => prev_state = updated,
+            }
+        }
+    }
+
+    fn get_epoch_relative_timestamp(
+        get_timestamp: &T,
+        epoch: u64,
+    ) -> Result<u64, SnowflakeGeneratorError> {
+        Self::CONFIG.epoch_relative(get_timestamp.timestamp()?, epoch)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::atomic::AtomicU64;
+
+    use super::*;
+
+    #[test]
+    fn test_const_matches_runtime() {
+        let clock = AtomicU64::new(0x1234);
+        let get_timestamp = || Ok(clock.load(Ordering::SeqCst));
+        let generator =
+            ConstSnowflakeGenerator::<42, 5, 2, _>::new(0x10, 0, get_timestamp).unwrap();
+        let runtime = crate::SnowflakeGenerator::new_with_config(
+            0x10,
+            0,
+            get_timestamp,
+            SnowflakeConfig::new(42, 5, 2).unwrap(),
+        )
+        .unwrap();
+
+        for _ in 0..4 {
+            assert_eq!(generator.generate().unwrap(), runtime.generate().unwrap());
+        }
+        assert!(matches!(
+            generator.generate(),
+            Err(SnowflakeGeneratorError::SequenceOverflow)
+        ));
+
+        clock.store(0x1235, Ordering::SeqCst);
+        assert_eq!(generator.generate().unwrap(), runtime.generate().unwrap());
+        clock.store(0x1200, Ordering::SeqCst);
+        assert_eq!(generator.generate().unwrap(), runtime.generate().unwrap());
+    }
+
+    #[test]
+    fn test_const_machine_id_overflow() {
+        let result = ConstSnowflakeGenerator::<42, 5, 2, _>::new(32, 0, || Ok(0));
+        assert!(matches!(
+            result,
+            Err(SnowflakeGeneratorError::MachineIdOverflow)
+        ));
+    }
+}
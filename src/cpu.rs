@@ -0,0 +1,154 @@
+//! Snowflakes that record which CPU core generated them.
+//!
+//! Only Linux is supported, as the current core is read with `sched_getcpu`.
+//! The low bits of the sequence are given over to the core ID, so the number of
+//! snowflakes that can be generated per millisecond drops to
+//! `2^(sequence_bits - core_id_bits)`. The core ID is masked to fit, so on boxes
+//! with more cores than `2^core_id_bits` several cores share a value.
+
+use crate::{
+    build_mask, SnowflakeConfig, SnowflakeGenerator, SnowflakeGeneratorError, TimestampSource,
+};
+
+/// A generator that packs the current CPU core into the low sequence bits.
+///
+/// Uniqueness comes from the counter in the rest of the sequence bits, the core
+/// ID is purely informational and can be recovered with [split_core_id].
+pub struct CpuSnowflakeGenerator<T>
+where
+    T: TimestampSource,
+{
+    inner: SnowflakeGenerator<T>,
+    config: SnowflakeConfig,
+    core_id_bits: u64,
+}
+
+impl<T: TimestampSource> CpuSnowflakeGenerator<T> {
+    /// Create a new CpuSnowflakeGenerator with default configuration.
+    pub fn new(
+        machine_id: u32,
+        epoch: u64,
+        get_timestamp: T,
+        core_id_bits: u64,
+    ) -> Result<Self, SnowflakeGeneratorError> {
+        let config = SnowflakeConfig::default();
+        Self::new_with_config(machine_id, epoch, get_timestamp, config, core_id_bits)
+    }
+
+    /// Create a new CpuSnowflakeGenerator with a custom configuration.
+    ///
+    /// The core ID bits are taken from the sequence bits, and must leave at least
+    /// one bit for the counter.
+    pub fn new_with_config(
+        machine_id: u32,
+        epoch: u64,
+        get_timestamp: T,
+        config: SnowflakeConfig,
+        core_id_bits: u64,
+    ) -> Result<Self, SnowflakeGeneratorError> {
+        if core_id_bits == 0 {
+            return Err(SnowflakeGeneratorError::InvalidBitConfig);
+        }
+
+        let counter_config = config.with_sequence_split(core_id_bits)?;
+        let inner =
+            SnowflakeGenerator::new_with_config(machine_id, epoch, get_timestamp, counter_config)?;
+
+        Ok(Self {
+            inner,
+            config,
+            core_id_bits,
+        })
+    }
+
+    /// Generate a new snowflake tagged with the current CPU core.
+    ///
+    /// Returns [SnowflakeGeneratorError::CoreIdUnavailable] if the current core
+    /// can't be determined.
+    pub fn generate(&self) -> Result<u64, SnowflakeGeneratorError> {
+        let core_id = current_core_id()? & build_mask(self.core_id_bits);
+        let snowflake = self.inner.generate()?;
+
+        let counter_config = &self.inner.config;
+        let machine_id = counter_config.machine_id_of(snowflake);
+        let counter = counter_config.sequence_of(snowflake);
+        Ok(self.config.pack(
+            counter_config.timestamp_of(snowflake),
+            machine_id,
+            counter << self.core_id_bits | core_id,
+        ))
+    }
+}
+
+/// Split the sequence of a snowflake into its counter and core ID.
+///
+/// The snowflake should come from a [CpuSnowflakeGenerator] using the same
+/// config and number of core ID bits.
+pub fn split_core_id(snowflake: u64, core_id_bits: u64, config: &SnowflakeConfig) -> (u64, u64) {
+    let sequence = config.sequence_of(snowflake);
+    (
+        sequence >> core_id_bits,
+        sequence & build_mask(core_id_bits),
+    )
+}
+
+fn current_core_id() -> Result<u64, SnowflakeGeneratorError> {
+    // SAFETY: sched_getcpu takes no arguments and only reports the current CPU.
+    let core_id = unsafe { libc::sched_getcpu() };
+    if core_id < 0 {
+        Err(SnowflakeGeneratorError::CoreIdUnavailable)
+    } else {
+        Ok(core_id as u64)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_split_core_id() {
+        let config = SnowflakeConfig::default();
+        let (counter, core_id) = split_core_id(0x48D010235, 4, &config);
+        assert_eq!(counter, 0x23);
+        assert_eq!(core_id, 0x5);
+    }
+
+    #[test]
+    fn test_core_id_generation() {
+        let config = SnowflakeConfig::default();
+        let generator = CpuSnowflakeGenerator::new(0x10, 0, || Ok(0x1234), 4).unwrap();
+
+        for expected_counter in 0..3 {
+            let snowflake = generator.generate().unwrap();
+            let (counter, core_id) = split_core_id(snowflake, 4, &config);
+            assert_eq!(counter, expected_counter);
+            assert!(core_id < 16);
+            assert_eq!(config.timestamp_of(snowflake), 0x1234);
+            assert_eq!(config.machine_id_of(snowflake), 0x10);
+        }
+    }
+
+    #[test]
+    fn test_core_id_reduces_capacity() {
+        let generator = CpuSnowflakeGenerator::new(0x10, 0, || Ok(0x1234), 4).unwrap();
+        for _ in 0..256 {
+            generator.generate().unwrap();
+        }
+
+        let result = generator.generate();
+        assert!(matches!(
+            result,
+            Err(SnowflakeGeneratorError::SequenceOverflow)
+        ));
+    }
+
+    #[test]
+    fn test_core_id_bits_must_leave_counter() {
+        let result = CpuSnowflakeGenerator::new(0x10, 0, || Ok(0x1234), 12);
+        assert!(matches!(
+            result,
+            Err(SnowflakeGeneratorError::InvalidBitConfig)
+        ));
+    }
+}
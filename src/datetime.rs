@@ -0,0 +1,179 @@
+//! Integration with `chrono` for working with snowflake timestamps as dates.
+
+use chrono::{DateTime, TimeDelta, Utc};
+
+use crate::{
+    SequenceAllocator, SnowflakeConfig, SnowflakeGenerator, SnowflakeGeneratorError,
+    TimestampSource,
+};
+
+/// The current time in milliseconds since the UNIX epoch, read through `chrono`.
+///
+/// Like [crate::unix_millis], this can be passed straight to a generator as its
+/// timestamp function.
+///
+/// ```rust
+/// use frostbit::{chrono_now_millis, SnowflakeGenerator};
+///
+/// let gen = SnowflakeGenerator::new(0, 1_288_834_974_657, chrono_now_millis).unwrap();
+/// let snowflake = gen.generate().unwrap();
+/// assert!(gen.decode_datetime_utc(snowflake) <= chrono::Utc::now());
+/// ```
+#[cfg(feature = "std")]
+pub fn chrono_now_millis() -> Result<u64, &'static str> {
+    u64::try_from(Utc::now().timestamp_millis()).map_err(|_| "Clock is before the UNIX epoch")
+}
+
+impl SnowflakeConfig {
+    /// The date at which the timestamp field overflows for the given epoch.
+    ///
+    /// From this date on, generation fails with
+    /// [crate::SnowflakeGeneratorError::TimestampOverflow]. The epoch is in the
+    /// [crate::TimeUnit] of the config. Dates past what `chrono` can represent are
+    /// clamped to [DateTime::<Utc>::MAX_UTC].
+    pub fn overflow_date(&self, epoch: u64) -> DateTime<Utc> {
+        let overflow = self
+            .time_unit
+            .to_duration(epoch.saturating_add(self.timestamp_max));
+        TimeDelta::from_std(overflow)
+            .ok()
+            .and_then(|overflow| DateTime::UNIX_EPOCH.checked_add_signed(overflow))
+            .unwrap_or(DateTime::<Utc>::MAX_UTC)
+    }
+
+    /// Build the snowflake for a date, machine ID, and sequence.
+    ///
+    /// Meant for writing test fixtures from human readable dates, which are rounded
+    /// down to the [crate::TimeUnit] of the config. Unlike generation, nothing is
+    /// masked to fit, so out of range inputs are reported as errors:
+    /// [SnowflakeGeneratorError::TimestampBelowFloor] for dates before the epoch or
+    /// the minimum timestamp, [SnowflakeGeneratorError::TimestampOverflow] for dates
+    /// past [SnowflakeConfig::overflow_date], and
+    /// [SnowflakeGeneratorError::MachineIdOverflow] or
+    /// [SnowflakeGeneratorError::SequenceOverflow] for fields that don't fit.
+    pub fn encode_at(
+        &self,
+        datetime: DateTime<Utc>,
+        epoch: u64,
+        machine_id: u64,
+        sequence: u64,
+    ) -> Result<u64, SnowflakeGeneratorError> {
+        let timestamp_ms = (datetime - DateTime::UNIX_EPOCH)
+            .to_std()
+            .ok()
+            .map(|since_unix_epoch| self.time_unit.from_duration(since_unix_epoch))
+            .and_then(|timestamp_ms| timestamp_ms.checked_sub(epoch))
+            .filter(|timestamp_ms| *timestamp_ms >= self.min_timestamp_ms)
+            .ok_or(SnowflakeGeneratorError::TimestampBelowFloor)?;
+
+        if timestamp_ms >= self.timestamp_max {
+            Err(SnowflakeGeneratorError::TimestampOverflow)
+        } else if machine_id > self.machine_id_mask {
+            Err(SnowflakeGeneratorError::MachineIdOverflow)
+        } else if sequence > self.sequence_max {
+            Err(SnowflakeGeneratorError::SequenceOverflow)
+        } else {
+            Ok(self.pack(timestamp_ms, machine_id, sequence))
+        }
+    }
+}
+
+impl<T: TimestampSource, S: SequenceAllocator> SnowflakeGenerator<T, S> {
+    /// The UTC date at which a snowflake was generated.
+    ///
+    /// Like [SnowflakeGenerator::decode_timestamp], the epoch is added back and the
+    /// timestamp field converted from the [crate::TimeUnit] of the config, assuming
+    /// the timestamp function counts from the UNIX epoch. Dates past what `chrono`
+    /// can represent are clamped to [DateTime::<Utc>::MAX_UTC].
+    pub fn decode_datetime_utc(&self, snowflake: u64) -> DateTime<Utc> {
+        TimeDelta::from_std(self.decode_timestamp(snowflake))
+            .ok()
+            .and_then(|since_unix_epoch| DateTime::UNIX_EPOCH.checked_add_signed(since_unix_epoch))
+            .unwrap_or(DateTime::<Utc>::MAX_UTC)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use chrono::NaiveDate;
+
+    const TWITTER_EPOCH: u64 = 1_288_834_974_657;
+
+    #[test]
+    fn test_overflow_date() {
+        let overflow_date = SnowflakeConfig::default().overflow_date(TWITTER_EPOCH);
+        let expected = NaiveDate::from_ymd_opt(2080, 7, 10)
+            .and_then(|date| date.and_hms_milli_opt(17, 30, 30, 208))
+            .unwrap()
+            .and_utc();
+        assert_eq!(overflow_date, expected);
+    }
+
+    #[test]
+    fn test_overflow_date_clamped() {
+        let config = SnowflakeConfig::new(60, 2, 2).unwrap();
+        assert_eq!(config.overflow_date(0), DateTime::<Utc>::MAX_UTC);
+    }
+
+    #[test]
+    fn test_encode_at() {
+        let config = SnowflakeConfig::default();
+        let datetime = NaiveDate::from_ymd_opt(2023, 1, 1)
+            .and_then(|date| date.and_hms_opt(0, 0, 0))
+            .unwrap()
+            .and_utc();
+
+        let snowflake = config.encode_at(datetime, TWITTER_EPOCH, 5, 0).unwrap();
+        assert_eq!(snowflake, 1_609_338_612_741_066_752);
+        assert_eq!(
+            config.timestamp_of(snowflake) + TWITTER_EPOCH,
+            1_672_531_200_000
+        );
+        assert_eq!(config.machine_id_of(snowflake), 5);
+    }
+
+    #[test]
+    fn test_encode_at_out_of_range() {
+        let config = SnowflakeConfig::default();
+        let before_epoch = DateTime::from_timestamp_millis(TWITTER_EPOCH as i64 - 1).unwrap();
+        assert!(matches!(
+            config.encode_at(before_epoch, TWITTER_EPOCH, 5, 0),
+            Err(SnowflakeGeneratorError::TimestampBelowFloor)
+        ));
+        assert!(matches!(
+            config.encode_at(config.overflow_date(TWITTER_EPOCH), TWITTER_EPOCH, 5, 0),
+            Err(SnowflakeGeneratorError::TimestampOverflow)
+        ));
+
+        let datetime = DateTime::from_timestamp_millis(TWITTER_EPOCH as i64).unwrap();
+        assert!(matches!(
+            config.encode_at(datetime, TWITTER_EPOCH, 1024, 0),
+            Err(SnowflakeGeneratorError::MachineIdOverflow)
+        ));
+        assert!(matches!(
+            config.encode_at(datetime, TWITTER_EPOCH, 5, 4096),
+            Err(SnowflakeGeneratorError::SequenceOverflow)
+        ));
+    }
+
+    #[test]
+    fn test_decode_datetime_utc() {
+        let config = SnowflakeConfig::new(52, 5, 6)
+            .unwrap()
+            .with_time_unit(crate::TimeUnit::Micros)
+            .unwrap();
+        let epoch_us = TWITTER_EPOCH * 1_000;
+        let generator = SnowflakeGenerator::new_with_config(
+            0x10,
+            epoch_us,
+            || Ok(1_672_531_200_000_250),
+            config,
+        )
+        .unwrap();
+
+        let snowflake = generator.generate().unwrap();
+        let expected = DateTime::from_timestamp_micros(1_672_531_200_000_250).unwrap();
+        assert_eq!(generator.decode_datetime_utc(snowflake), expected);
+    }
+}
@@ -0,0 +1,243 @@
+//! Snowflakes coordinated across processes through a memory-mapped state file.
+//!
+//! The packed timestamp and sequence normally live in an atomic owned by the
+//! generator. Here they live in a small file that every process on the host maps
+//! into memory, so independent processes share one atomic and run the same CAS
+//! loop against it. This only works between processes that see the same page
+//! cache, so the file must be on a local filesystem.
+//!
+//! ## Locking
+//!
+//! The file carries a header with the layout of the config and the epoch. Opening
+//! a generator takes an exclusive advisory lock on the file while the header is
+//! written or checked, so two processes racing to create the file can't both set
+//! it up. A file whose header was never written, because a process crashed while
+//! creating it, is set up again on the next open. The lock is released once the
+//! file is mapped. Generation itself never
+//! takes the lock, it relies on the mapped atomic alone.
+//!
+//! ## Durability
+//!
+//! Updates to the state are visible to every process straight away, and survive
+//! any of them crashing. They are not flushed to disk, so the last state can be
+//! lost if the host goes down. This is harmless as long as the clock has moved on
+//! by the time the processes come back up, which is the same guarantee a plain
+//! [crate::SnowflakeGenerator] gives after a restart.
+
+use std::fs::{File, OpenOptions};
+use std::io;
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use memmap2::MmapRaw;
+
+use crate::timestamp_sequence::SequencePacking;
+use crate::{
+    SequenceAllocator, SnowflakeConfig, SnowflakeGenerator, SnowflakeGeneratorError,
+    TimestampSequence, TimestampSource,
+};
+
+const STATE_WORD: usize = 0;
+const LAYOUT_WORD: usize = 1;
+const EPOCH_WORD: usize = 2;
+const STATE_FILE_LEN: u64 = 3 * 8;
+const STATE_FILE_MAGIC: u64 = 0x4652_5354 << 32;
+
+/// A generator whose timestamp and sequence are shared through a state file.
+///
+/// Every process that opens a FileBackedGenerator on the same path shares the same
+/// sequence, so they never hand out the same timestamp and sequence pair. They
+/// must all use the same config and epoch, which is checked when opening.
+pub struct FileBackedGenerator<T>
+where
+    T: TimestampSource,
+{
+    inner: SnowflakeGenerator<T, FileSequence>,
+}
+
+impl<T: TimestampSource> FileBackedGenerator<T> {
+    /// Open a FileBackedGenerator, creating the state file if it doesn't exist.
+    ///
+    /// Returns [SnowflakeGeneratorError::StateFileError] if the file can't be set
+    /// up, or if it was created with a different config or epoch.
+    pub fn open<P: AsRef<Path>>(
+        path: P,
+        machine_id: u32,
+        epoch: u64,
+        get_timestamp: T,
+        config: SnowflakeConfig,
+    ) -> Result<Self, SnowflakeGeneratorError> {
+        let sequence = FileSequence::open(path.as_ref(), epoch, config)
+            .map_err(SnowflakeGeneratorError::StateFileError)?;
+        let inner = SnowflakeGenerator::new_with_allocator(
+            machine_id,
+            epoch,
+            get_timestamp,
+            config,
+            sequence,
+        )?;
+        Ok(Self { inner })
+    }
+
+    /// Generate a new snowflake.
+    ///
+    /// This behaves like [SnowflakeGenerator::generate], with the sequence shared
+    /// between every generator using the same state file.
+    pub fn generate(&self) -> Result<u64, SnowflakeGeneratorError> {
+        self.inner.generate()
+    }
+}
+
+struct FileSequence {
+    map: MmapRaw,
+    packing: SequencePacking,
+}
+
+impl FileSequence {
+    fn open(path: &Path, epoch: u64, config: SnowflakeConfig) -> io::Result<Self> {
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(path)?;
+
+        file.lock()?;
+        let result = Self::map(&file, epoch, config);
+        file.unlock()?;
+        result
+    }
+
+    fn map(file: &File, epoch: u64, config: SnowflakeConfig) -> io::Result<Self> {
+        let len = file.metadata()?.len();
+        if len == 0 {
+            file.set_len(STATE_FILE_LEN)?;
+        } else if len != STATE_FILE_LEN {
+            return Err(invalid_state_file("unexpected state file length"));
+        }
+
+        let sequence = Self {
+            map: MmapRaw::map_raw(file)?,
+            packing: SequencePacking::new(config),
+        };
+
+        // The layout word is never zero once written, thanks to the magic, so a zero
+        // means the header was never written, e.g. because a process crashed right
+        // after sizing the file. The layout goes in last, to mark the header done.
+        let layout = layout_word(&config);
+        match sequence.word(LAYOUT_WORD).load(Ordering::SeqCst) {
+            0 => {
+                sequence.word(EPOCH_WORD).store(epoch, Ordering::SeqCst);
+                sequence.word(LAYOUT_WORD).store(layout, Ordering::SeqCst);
+            }
+            stored
+                if stored != layout
+                    || sequence.word(EPOCH_WORD).load(Ordering::SeqCst) != epoch =>
+            {
+                return Err(invalid_state_file("state file uses a different config"));
+            }
+            _ => {}
+        }
+
+        Ok(sequence)
+    }
+
+    fn word(&self, index: usize) -> &AtomicU64 {
+        // SAFETY: the map is page aligned and STATE_FILE_LEN long, so every word is
+        // in bounds and aligned. The map lives as long as self, and is only ever
+        // accessed through atomics, including by other processes.
+        unsafe { AtomicU64::from_ptr(self.map.as_mut_ptr().cast::<u64>().add(index)) }
+    }
+}
+
+impl SequenceAllocator for FileSequence {
+    fn allocate(&self, timestamp: u64) -> Result<TimestampSequence, SnowflakeGeneratorError> {
+        self.packing
+            .increment_sequence(self.word(STATE_WORD), timestamp)
+    }
+}
+
+/// Everything about the config that changes how the state word is packed.
+fn layout_word(config: &SnowflakeConfig) -> u64 {
+    STATE_FILE_MAGIC
+        | config.timestamp_shift() << 16
+        | (config.timestamp_bits + config.era_bits) << 8
+        | config.sequence_bits
+}
+
+fn invalid_state_file(message: &'static str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::collections::HashSet;
+
+    #[test]
+    fn test_shared_state_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("frostbit.state");
+
+        let handles: Vec<_> = (0..2)
+            .map(|_| {
+                let path = path.clone();
+                std::thread::spawn(move || {
+                    let config = SnowflakeConfig::default();
+                    let generator =
+                        FileBackedGenerator::open(path, 0x10, 0, || Ok(0x1234), config).unwrap();
+                    (0..1000)
+                        .map(|_| generator.generate().unwrap())
+                        .collect::<Vec<_>>()
+                })
+            })
+            .collect();
+
+        let snowflakes: HashSet<u64> = handles
+            .into_iter()
+            .flat_map(|handle| handle.join().unwrap())
+            .collect();
+        assert_eq!(snowflakes.len(), 2000);
+    }
+
+    #[test]
+    fn test_state_survives_reopen() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("frostbit.state");
+        let config = SnowflakeConfig::default();
+
+        let first = FileBackedGenerator::open(&path, 0x10, 0, || Ok(0x1234), config).unwrap();
+        assert_eq!(first.generate().unwrap(), 0x48d010000);
+        drop(first);
+
+        let second = FileBackedGenerator::open(&path, 0x10, 0, || Ok(0x1234), config).unwrap();
+        assert_eq!(second.generate().unwrap(), 0x48d010001);
+    }
+
+    #[test]
+    fn test_unwritten_header_is_set_up() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("frostbit.state");
+        std::fs::write(&path, [0; STATE_FILE_LEN as usize]).unwrap();
+
+        let config = SnowflakeConfig::default();
+        let generator = FileBackedGenerator::open(&path, 0x10, 0, || Ok(0x1234), config).unwrap();
+        assert_eq!(generator.generate().unwrap(), 0x48d010000);
+    }
+
+    #[test]
+    fn test_state_file_config_mismatch() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("frostbit.state");
+
+        let config = SnowflakeConfig::default();
+        FileBackedGenerator::open(&path, 0x10, 0, || Ok(0x1234), config).unwrap();
+
+        let config = SnowflakeConfig::new(41, 12, 10).unwrap();
+        let result = FileBackedGenerator::open(&path, 0x10, 0, || Ok(0x1234), config);
+        assert!(matches!(
+            result,
+            Err(SnowflakeGeneratorError::StateFileError(_))
+        ));
+    }
+}
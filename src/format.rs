@@ -0,0 +1,210 @@
+//! Textual representations of snowflakes.
+
+#[cfg(feature = "alloc")]
+use alloc::{format, string::String, vec::Vec};
+
+#[cfg(feature = "alloc")]
+use crate::build_mask;
+use crate::{SnowflakeConfig, SnowflakeGeneratorError};
+
+#[cfg(feature = "alloc")]
+const BASE62_ALPHABET: &[u8; 62] =
+    b"0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz";
+
+/// Render a snowflake as a diagram of the bits in each field.
+///
+/// Fields are listed from the most significant to the least significant, as
+/// described by [SnowflakeConfig::layout]. For example, with a 4-bit timestamp,
+/// 3-bit machine ID, and 2-bit sequence:
+///
+/// ```rust
+/// use frostbit::{format_bits, SnowflakeConfig};
+///
+/// let config = SnowflakeConfig::new(4, 3, 2).unwrap();
+/// let diagram = format_bits(0b1_0011_0111, &config);
+/// assert_eq!(diagram, "[timestamp:1001|machine_id:101|sequence:11]");
+/// ```
+#[cfg(feature = "alloc")]
+pub fn format_bits(id: u64, config: &SnowflakeConfig) -> String {
+    let fields = config
+        .layout()
+        .iter()
+        .map(|field| {
+            let value = (id >> field.shift) & build_mask(field.width);
+            format!(
+                "{}:{:0width$b}",
+                field.name,
+                value,
+                width = field.width as usize
+            )
+        })
+        .collect::<Vec<_>>();
+
+    format!("[{}]", fields.join("|"))
+}
+
+/// Encode a snowflake in base62, for compact IDs in URLs.
+///
+/// The digits are `0-9`, `A-Z`, then `a-z`, most significant first and without
+/// padding, so any snowflake takes at most 11 characters. The encoding is stable
+/// and round-trips exactly through [decode_base62].
+#[cfg(feature = "alloc")]
+pub fn encode_base62(snowflake: u64) -> String {
+    let mut digits = Vec::new();
+    let mut remaining = snowflake;
+    loop {
+        digits.push(BASE62_ALPHABET[(remaining % 62) as usize]);
+        remaining /= 62;
+        if remaining == 0 {
+            break;
+        }
+    }
+
+    digits.iter().rev().map(|digit| *digit as char).collect()
+}
+
+/// Decode a snowflake encoded with [encode_base62].
+///
+/// Returns [SnowflakeGeneratorError::InvalidEncoding] if the string is empty,
+/// contains anything but base62 digits, or is too large for a `u64`.
+pub fn decode_base62(encoded: &str) -> Result<u64, SnowflakeGeneratorError> {
+    if encoded.is_empty() {
+        return Err(SnowflakeGeneratorError::InvalidEncoding);
+    }
+
+    encoded.bytes().try_fold(0u64, |snowflake, digit| {
+        let value = match digit {
+            b'0'..=b'9' => digit - b'0',
+            b'A'..=b'Z' => digit - b'A' + 10,
+            b'a'..=b'z' => digit - b'a' + 36,
+            _ => return Err(SnowflakeGeneratorError::InvalidEncoding),
+        };
+        snowflake
+            .checked_mul(62)
+            .and_then(|snowflake| snowflake.checked_add(value as u64))
+            .ok_or(SnowflakeGeneratorError::InvalidEncoding)
+    })
+}
+
+/// Encode a snowflake as lowercase hex, zero-padded to the width of the config.
+///
+/// The width is the total number of bits of the config rounded up to whole hex
+/// digits, so every snowflake of a config takes the same number of characters, 16
+/// for the default layout. Bits above the config are dropped. The encoding
+/// round-trips through [decode_hex].
+///
+/// ```rust
+/// use frostbit::{encode_hex, SnowflakeConfig};
+///
+/// let config = SnowflakeConfig::new(20, 5, 8).unwrap();
+/// assert_eq!(encode_hex(0x1234, &config), "000001234");
+/// ```
+#[cfg(feature = "alloc")]
+pub fn encode_hex(snowflake: u64, config: &SnowflakeConfig) -> String {
+    let fields = snowflake & u64::MAX >> (64 - config.total_bits());
+    format!("{fields:0width$x}", width = hex_width(config))
+}
+
+/// Decode a snowflake encoded with [encode_hex].
+///
+/// Upper case digits are accepted too. Returns
+/// [SnowflakeGeneratorError::InvalidEncoding] if the string isn't exactly as wide as
+/// [encode_hex] makes it, contains anything but hex digits, or sets bits above the
+/// config.
+pub fn decode_hex(encoded: &str, config: &SnowflakeConfig) -> Result<u64, SnowflakeGeneratorError> {
+    if encoded.len() != hex_width(config) || !encoded.bytes().all(|digit| digit.is_ascii_hexdigit())
+    {
+        return Err(SnowflakeGeneratorError::InvalidEncoding);
+    }
+
+    u64::from_str_radix(encoded, 16)
+        .ok()
+        .filter(|snowflake| config.fits(*snowflake))
+        .ok_or(SnowflakeGeneratorError::InvalidEncoding)
+}
+
+fn hex_width(config: &SnowflakeConfig) -> usize {
+    config.total_bits().div_ceil(4) as usize
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn test_format_bits_default() {
+        let config = SnowflakeConfig::default();
+        let diagram = format_bits(0x48D010007, &config);
+        assert_eq!(
+            diagram,
+            "[timestamp:00000000000000000000000000001001000110100\
+             |machine_id:0000010000|sequence:000000000111]"
+        );
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn test_format_bits_era() {
+        let config = SnowflakeConfig::new(4, 3, 2)
+            .unwrap()
+            .with_era_bits(1)
+            .unwrap();
+        let diagram = format_bits(0b1_1001_0111, &config);
+        assert_eq!(diagram, "[era:1|timestamp:1001|machine_id:01|sequence:11]");
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn test_base62_round_trip() {
+        for snowflake in [0, 61, 62, 0x48d010235, 1_541_815_603_606_036_480, u64::MAX] {
+            assert_eq!(decode_base62(&encode_base62(snowflake)).unwrap(), snowflake);
+        }
+        assert_eq!(encode_base62(0), "0");
+        assert_eq!(encode_base62(62), "10");
+        assert_eq!(encode_base62(u64::MAX), "LygHa16AHYF");
+    }
+
+    #[test]
+    fn test_base62_invalid() {
+        for encoded in ["", "abc-def", "LygHa16AHYG", "100000000000"] {
+            assert!(matches!(
+                decode_base62(encoded),
+                Err(SnowflakeGeneratorError::InvalidEncoding)
+            ));
+        }
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn test_hex_round_trip() {
+        let config = SnowflakeConfig::default();
+        for snowflake in [0, 0x48d010235, (1 << 63) - 1] {
+            let encoded = encode_hex(snowflake, &config);
+            assert_eq!(encoded.len(), 16);
+            assert_eq!(decode_hex(&encoded, &config).unwrap(), snowflake);
+        }
+        assert_eq!(encode_hex(0x48d010235, &config), "000000048d010235");
+        assert_eq!(
+            decode_hex("000000048D010235", &config).unwrap(),
+            0x48d010235
+        );
+    }
+
+    #[test]
+    fn test_hex_invalid() {
+        let config = SnowflakeConfig::default();
+        for encoded in [
+            "",
+            "48d010235",
+            "+00000048d010235",
+            "000000048d01023g",
+            "8000000000000000",
+        ] {
+            assert!(matches!(
+                decode_hex(encoded, &config),
+                Err(SnowflakeGeneratorError::InvalidEncoding)
+            ));
+        }
+    }
+}
@@ -0,0 +1,221 @@
+//! Iterators over freshly generated snowflakes.
+
+use crate::{SequenceAllocator, SnowflakeGenerator, SnowflakeGeneratorError, TimestampSource};
+
+impl<T, S> SnowflakeGenerator<T, S>
+where
+    T: TimestampSource,
+    S: SequenceAllocator,
+{
+    /// Iterate over new snowflakes until generation fails.
+    ///
+    /// The iterator yields plain snowflakes and ends on the first error, typically
+    /// [SnowflakeGeneratorError::SequenceOverflow] once the current millisecond is
+    /// used up. The error is kept for inspection with [UntilOverflow::last_error].
+    ///
+    /// ```rust
+    /// use frostbit::{SnowflakeGenerator, SnowflakeGeneratorError};
+    ///
+    /// let gen = SnowflakeGenerator::new(0, 0, || Ok(0)).unwrap();
+    /// let mut iter = gen.iter_until_overflow();
+    /// assert_eq!(iter.by_ref().count(), 4096);
+    /// assert!(matches!(iter.last_error(), Some(SnowflakeGeneratorError::SequenceOverflow)));
+    /// ```
+    pub fn iter_until_overflow(&self) -> UntilOverflow<'_, T, S> {
+        UntilOverflow {
+            generator: self,
+            last_error: None,
+        }
+    }
+
+    /// Iterate over the results of generating new snowflakes.
+    ///
+    /// Every item is the result of a call to [SnowflakeGenerator::generate]. The first
+    /// error is yielded like any other item, after which the iterator ends.
+    ///
+    /// ```rust
+    /// use frostbit::SnowflakeGenerator;
+    ///
+    /// let gen = SnowflakeGenerator::new(0, 0, || Ok(0)).unwrap();
+    /// let snowflakes: Result<Vec<u64>, _> = gen.iter().take(1000).collect();
+    /// assert_eq!(snowflakes.unwrap().len(), 1000);
+    /// ```
+    pub fn iter(&self) -> Iter<'_, T, S> {
+        Iter {
+            generator: self,
+            failed: false,
+        }
+    }
+
+    /// Fill a slice with new snowflakes, without allocating.
+    ///
+    /// Stops at the first error and returns it, in which case only the snowflakes
+    /// before the failed one have been written.
+    pub fn generate_n(&self, snowflakes: &mut [u64]) -> Result<(), SnowflakeGeneratorError> {
+        for snowflake in snowflakes {
+            *snowflake = self.generate()?;
+        }
+
+        Ok(())
+    }
+}
+
+/// An iterator over the results of generating new snowflakes.
+///
+/// Returned by [SnowflakeGenerator::iter]. It ends right after yielding an error.
+pub struct Iter<'a, T, S>
+where
+    T: TimestampSource,
+    S: SequenceAllocator,
+{
+    generator: &'a SnowflakeGenerator<T, S>,
+    failed: bool,
+}
+
+impl<T, S> Iterator for Iter<'_, T, S>
+where
+    T: TimestampSource,
+    S: SequenceAllocator,
+{
+    type Item = Result<u64, SnowflakeGeneratorError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed {
+            return None;
+        }
+
+        let result = self.generator.generate();
+        self.failed = result.is_err();
+        Some(result)
+    }
+}
+
+impl<T, S> core::iter::FusedIterator for Iter<'_, T, S>
+where
+    T: TimestampSource,
+    S: SequenceAllocator,
+{
+}
+
+/// An iterator over new snowflakes that ends on the first error.
+///
+/// Returned by [SnowflakeGenerator::iter_until_overflow]. Once it has ended it
+/// keeps returning `None`, even if the clock has since moved on.
+pub struct UntilOverflow<'a, T, S>
+where
+    T: TimestampSource,
+    S: SequenceAllocator,
+{
+    generator: &'a SnowflakeGenerator<T, S>,
+    last_error: Option<SnowflakeGeneratorError>,
+}
+
+impl<T, S> UntilOverflow<'_, T, S>
+where
+    T: TimestampSource,
+    S: SequenceAllocator,
+{
+    /// The error that ended the iterator, or `None` if it hasn't ended yet.
+    pub fn last_error(&self) -> Option<&SnowflakeGeneratorError> {
+        self.last_error.as_ref()
+    }
+}
+
+impl<T, S> Iterator for UntilOverflow<'_, T, S>
+where
+    T: TimestampSource,
+    S: SequenceAllocator,
+{
+    type Item = u64;
+
+    fn next(&mut self) -> Option<u64> {
+        if self.last_error.is_some() {
+            return None;
+        }
+
+        match self.generator.generate() {
+            Ok(snowflake) => Some(snowflake),
+            Err(error) => {
+                self.last_error = Some(error);
+                None
+            }
+        }
+    }
+}
+
+impl<T, S> core::iter::FusedIterator for UntilOverflow<'_, T, S>
+where
+    T: TimestampSource,
+    S: SequenceAllocator,
+{
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::atomic::{AtomicU64, Ordering};
+
+    use super::*;
+    use crate::SnowflakeConfig;
+
+    #[test]
+    fn test_iter_until_overflow() {
+        let config = SnowflakeConfig::new(42, 5, 2).unwrap();
+        let clock = AtomicU64::new(0x1234);
+        let generator = SnowflakeGenerator::new_with_config(
+            0x10,
+            0,
+            || Ok(clock.load(Ordering::SeqCst)),
+            config,
+        )
+        .unwrap();
+
+        let mut iter = generator.iter_until_overflow();
+        assert!(iter.last_error().is_none());
+
+        let snowflakes = iter.by_ref().collect::<Vec<_>>();
+        let expected = (0..4)
+            .map(|sequence| config.pack(0x1234, 0x10, sequence))
+            .collect::<Vec<_>>();
+        assert_eq!(snowflakes, expected);
+        assert!(matches!(
+            iter.last_error(),
+            Some(SnowflakeGeneratorError::SequenceOverflow)
+        ));
+
+        clock.store(0x1235, Ordering::SeqCst);
+        assert_eq!(iter.next(), None);
+    }
+
+    #[test]
+    fn test_iter_yields_error() {
+        let config = SnowflakeConfig::new(42, 5, 2).unwrap();
+        let generator =
+            SnowflakeGenerator::new_with_config(0x10, 0, || Ok(0x1234), config).unwrap();
+
+        let results = generator.iter().collect::<Vec<_>>();
+        assert_eq!(results.len(), 5);
+        assert_eq!(results[3].as_ref().unwrap(), &config.pack(0x1234, 0x10, 3));
+        assert!(matches!(
+            results[4],
+            Err(SnowflakeGeneratorError::SequenceOverflow)
+        ));
+    }
+
+    #[test]
+    fn test_generate_n() {
+        let config = SnowflakeConfig::new(42, 5, 2).unwrap();
+        let generator =
+            SnowflakeGenerator::new_with_config(0x10, 0, || Ok(0x1234), config).unwrap();
+
+        let mut snowflakes = [0; 3];
+        generator.generate_n(&mut snowflakes).unwrap();
+        assert_eq!(snowflakes[2], config.pack(0x1234, 0x10, 2));
+
+        let result = generator.generate_n(&mut snowflakes);
+        assert!(matches!(
+            result,
+            Err(SnowflakeGeneratorError::SequenceOverflow)
+        ));
+        assert_eq!(snowflakes[0], config.pack(0x1234, 0x10, 3));
+    }
+}
@@ -0,0 +1,143 @@
+//! Snowflakes that can be traced back to what they were generated for.
+
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Mutex;
+
+use crate::{
+    SequenceAllocator, SnowflakeGenerator, SnowflakeGeneratorError, TimestampSequenceGenerator,
+    TimestampSource,
+};
+
+/// A generator that remembers a label for each snowflake it generates.
+///
+/// The labels live in a side table next to the generator, the snowflakes
+/// themselves are unchanged. This is meant for debugging, to find out what a
+/// snowflake was minted for. The table holds at most `capacity` labels, and once
+/// full the least recently generated or looked up label is evicted, so memory is
+/// bounded by `capacity` times the size of a label. Labels are lost when the
+/// generator is dropped.
+pub struct LabeledGenerator<T, S = TimestampSequenceGenerator>
+where
+    T: TimestampSource,
+    S: SequenceAllocator,
+{
+    inner: SnowflakeGenerator<T, S>,
+    labels: Mutex<LruLabels>,
+}
+
+impl<T, S> LabeledGenerator<T, S>
+where
+    T: TimestampSource,
+    S: SequenceAllocator,
+{
+    /// Wrap a generator, keeping up to `capacity` labels.
+    pub fn new(inner: SnowflakeGenerator<T, S>, capacity: usize) -> Self {
+        Self {
+            inner,
+            labels: Mutex::new(LruLabels::new(capacity)),
+        }
+    }
+
+    /// Generate a new snowflake and remember the label it was generated for.
+    pub fn generate_labeled(&self, label: String) -> Result<u64, SnowflakeGeneratorError> {
+        let snowflake = self.inner.generate()?;
+        self.lock_labels().insert(snowflake, label);
+        Ok(snowflake)
+    }
+
+    /// The label a snowflake was generated for.
+    ///
+    /// Returns `None` if the snowflake wasn't generated by this generator, or its
+    /// label has been evicted.
+    pub fn label_of(&self, id: u64) -> Option<String> {
+        self.lock_labels().get(id)
+    }
+
+    fn lock_labels(&self) -> std::sync::MutexGuard<'_, LruLabels> {
+        // the table is never left half updated, so a poisoned lock is still usable
+        self.labels
+            .lock()
+            .unwrap_or_else(|error| error.into_inner())
+    }
+}
+
+struct LruLabels {
+    labels: HashMap<u64, (String, u64)>,
+    recency: BTreeMap<u64, u64>,
+    capacity: usize,
+    tick: u64,
+}
+
+impl LruLabels {
+    fn new(capacity: usize) -> Self {
+        Self {
+            labels: HashMap::new(),
+            recency: BTreeMap::new(),
+            capacity,
+            tick: 0,
+        }
+    }
+
+    fn insert(&mut self, id: u64, label: String) {
+        if self.capacity == 0 {
+            return;
+        }
+
+        if self.labels.len() == self.capacity {
+            if let Some((_, oldest)) = self.recency.pop_first() {
+                self.labels.remove(&oldest);
+            }
+        }
+
+        let tick = self.next_tick();
+        self.labels.insert(id, (label, tick));
+        self.recency.insert(tick, id);
+    }
+
+    fn get(&mut self, id: u64) -> Option<String> {
+        let tick = self.next_tick();
+        let (label, last_used) = self.labels.get_mut(&id)?;
+        self.recency.remove(last_used);
+        self.recency.insert(tick, id);
+        *last_used = tick;
+        Some(label.clone())
+    }
+
+    fn next_tick(&mut self) -> u64 {
+        self.tick += 1;
+        self.tick
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_label_of() {
+        let generator = SnowflakeGenerator::new(0x10, 0, || Ok(0x1234)).unwrap();
+        let generator = LabeledGenerator::new(generator, 8);
+
+        let order = generator.generate_labeled("order".to_string()).unwrap();
+        let invoice = generator.generate_labeled("invoice".to_string()).unwrap();
+        assert_eq!(generator.label_of(order).as_deref(), Some("order"));
+        assert_eq!(generator.label_of(invoice).as_deref(), Some("invoice"));
+        assert_eq!(generator.label_of(0), None);
+    }
+
+    #[test]
+    fn test_label_eviction() {
+        let generator = SnowflakeGenerator::new(0x10, 0, || Ok(0x1234)).unwrap();
+        let generator = LabeledGenerator::new(generator, 2);
+
+        let first = generator.generate_labeled("first".to_string()).unwrap();
+        let second = generator.generate_labeled("second".to_string()).unwrap();
+        // looking up the first label makes the second the least recently used
+        assert!(generator.label_of(first).is_some());
+        let third = generator.generate_labeled("third".to_string()).unwrap();
+
+        assert_eq!(generator.label_of(first).as_deref(), Some("first"));
+        assert_eq!(generator.label_of(second), None);
+        assert_eq!(generator.label_of(third).as_deref(), Some("third"));
+    }
+}
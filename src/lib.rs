@@ -10,7 +10,8 @@
 //! There are other variants of snowflakes, such as the one defined
 //! by [Discord](https://discord.com/developers/docs/reference#snowflakes).
 //! It is possible to recreate these, by defining them as such in
-//! the [SnowFlakeConfig].
+//! the [SnowFlakeConfig], and the Discord and Instagram layouts come predefined
+//! as [SnowflakeConfig::discord] and [SnowflakeConfig::instagram].
 //!
 //! ## Example Usage:
 //!
@@ -18,22 +19,130 @@
 //! use frostbit::SnowflakeGenerator;
 //!
 //! let timestamp_fn = { move ||
-//!     // suggest using something like `chrono`
+//!     // suggest using something like `chrono`, or `frostbit::unix_millis`
 //!     Ok(0)
 //! };
 //!
 //! let gen = SnowflakeGenerator::new(0, 0, timestamp_fn).unwrap();
 //! let snowflake = gen.generate().unwrap();
 //! ```
+//!
+//! ## no_std
+//!
+//! The generators only rely on 64-bit atomics, so the crate works without the
+//! standard library on any target that has them. Disable the default `std`
+//! feature to build it that way. The `alloc` feature brings back the items that
+//! return collections, such as [SnowflakeGenerator::generate_batch] and
+//! [SnowflakeConfig::layout], while anything that needs the system clock,
+//! threads, or locks, such as [unix_millis] and [LabeledGenerator], stays behind
+//! `std`.
+
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
+
+#[cfg(feature = "alloc")]
+extern crate alloc;
 
+mod analysis;
+#[cfg(feature = "async")]
+mod async_generator;
+mod builder;
+mod const_generator;
+#[cfg(all(feature = "cpu-id", target_os = "linux"))]
+mod cpu;
+#[cfg(feature = "chrono")]
+mod datetime;
+#[cfg(all(feature = "mmap", not(loom)))]
+mod file_backed;
+mod format;
+mod iter;
+#[cfg(feature = "std")]
+mod labeled;
+#[cfg(feature = "hmac")]
+mod mac;
+mod machine_id;
+mod multi_epoch;
+mod nonce;
+#[cfg(feature = "time")]
+mod offset_datetime;
+#[cfg(feature = "opentelemetry")]
+mod otel;
+#[cfg(feature = "std")]
+mod pool;
+#[cfg(feature = "std")]
+mod recent;
+#[cfg(feature = "serde")]
+mod serialize;
+#[cfg(feature = "std")]
+mod sharded;
+mod snowflake;
+mod snowflake128;
+mod submilli;
 mod sync;
+#[cfg(any(test, feature = "testutil"))]
+pub mod testutil;
 mod timestamp_sequence;
+mod timestamp_source;
+mod tombstone;
 
-use timestamp_sequence::TimestampSequenceGenerator;
+#[cfg(feature = "alloc")]
+use alloc::{vec, vec::Vec};
+pub use analysis::{
+    approx_timestamp, cmp_by_timestamp, count_after, id_distance, partition_of,
+    partition_of_hashed, same_millisecond,
+};
+#[cfg(feature = "alloc")]
+pub use analysis::{decode_columnar, density_per_ms, detect_machine_id_reuse};
+#[cfg(feature = "async")]
+pub use async_generator::AsyncSnowflakeGenerator;
+pub use builder::SnowflakeGeneratorBuilder;
+pub use const_generator::ConstSnowflakeGenerator;
+#[cfg(all(feature = "cpu-id", target_os = "linux"))]
+pub use cpu::{split_core_id, CpuSnowflakeGenerator};
+#[cfg(all(feature = "chrono", feature = "std"))]
+pub use datetime::chrono_now_millis;
+#[cfg(all(feature = "mmap", not(loom)))]
+pub use file_backed::FileBackedGenerator;
+pub use format::{decode_base62, decode_hex};
+#[cfg(feature = "alloc")]
+pub use format::{encode_base62, encode_hex, format_bits};
+pub use iter::{Iter, UntilOverflow};
+#[cfg(feature = "std")]
+pub use labeled::LabeledGenerator;
+#[cfg(feature = "hmac")]
+pub use mac::{verify_hmac, HmacSnowflakeGenerator};
+pub use machine_id::machine_id_from_uid;
+#[cfg(all(feature = "std", target_os = "linux"))]
+pub use machine_id::{machine_id_from_hostname, machine_id_from_mac};
+pub use multi_epoch::rebase_snowflake;
+#[cfg(feature = "alloc")]
+pub use multi_epoch::MultiEpochDecoder;
+pub use nonce::NonceSnowflakeGenerator;
+#[cfg(feature = "opentelemetry")]
+pub use otel::TracedSnowflakeGenerator;
+#[cfg(feature = "std")]
+pub use pool::{LeasedGenerator, LeasedMachineId, MachineIdPool};
+#[cfg(feature = "std")]
+pub use recent::RecentIdFilter;
+#[cfg(feature = "std")]
+pub use sharded::ShardedSnowflakeGenerator;
+pub use snowflake::Snowflake;
+pub use snowflake128::{
+    worker_id_from_mac, DecodedSnowflake128, SnowflakeConfig128, SnowflakeGenerator128,
+};
+pub use submilli::SubMillisecondGenerator;
+use timestamp_sequence::OVERFLOW_GUARD_BITS;
+pub use timestamp_sequence::{SequenceAllocator, TimestampSequence, TimestampSequenceGenerator};
+pub use timestamp_source::{FallibleTimestamp, TimestampSource};
+pub use tombstone::{is_deleted, mark_deleted};
 
 const DEFAULT_TIMESTAMP_BITS: u64 = 41;
 const DEFAULT_MACHINE_ID_BITS: u64 = 10;
 const DEFAULT_SEQUENCE_ID_BITS: u64 = 12;
+const JS_SAFE_INTEGER_BITS: u64 = 53;
+const I64_SAFE_BITS: u64 = 63;
+const NANOS_PER_SECOND: u128 = 1_000_000_000;
+
+static ORDINAL: core::sync::atomic::AtomicU64 = core::sync::atomic::AtomicU64::new(0);
 
 /// Errors that can occur when generating snowflakes.
 ///
@@ -48,12 +157,89 @@ const DEFAULT_SEQUENCE_ID_BITS: u64 = 12;
 ///   function returns an error.
 /// - [SnowflakeGeneratorError::InvalidBitConfig] - When the configuration for
 ///   the snowflake generator is invalid.
+/// - [SnowflakeGeneratorError::WaitTimeout] - When generation had to wait for the
+///   clock to advance, and the deadline passed before it did.
+/// - [SnowflakeGeneratorError::MachineIdOverflow] - When the machine ID does not fit
+///   in the bits allocated for it, and the config uses [MachineIdPolicy::Reject].
+/// - [SnowflakeGeneratorError::CoreIdUnavailable] - When the OS cannot report which
+///   CPU core the current thread is running on.
+/// - [SnowflakeGeneratorError::FlavorOverflow] - When the flavor does not fit in the
+///   bits allocated for it.
+/// - [SnowflakeGeneratorError::TimestampBelowFloor] - When the timestamp is below
+///   the minimum set with [SnowflakeConfig::with_min_timestamp_ms].
+/// - [SnowflakeGeneratorError::StateFileError] - When the state file of a
+///   `FileBackedGenerator` can't be opened, locked, or mapped, or was set up with
+///   a different config or epoch.
+/// - [SnowflakeGeneratorError::MissingField] - When a required field wasn't set on a
+///   [SnowflakeGeneratorBuilder].
+/// - [SnowflakeGeneratorError::ClockWentBackwards] - When the clock went back further
+///   than allowed by [ClockBackwardsPolicy::Reject].
+/// - [SnowflakeGeneratorError::InvalidEncoding] - When a string can't be decoded as
+///   a snowflake.
+/// - [SnowflakeGeneratorError::EpochInFuture] - When the timestamp function returns
+///   a timestamp before the epoch.
+/// - [SnowflakeGeneratorError::MachineIdUnavailable] - When the hostname or MAC
+///   address a machine ID is derived from can't be read, or a [MachineIdPool] has
+///   no machine IDs left.
+/// - [SnowflakeGeneratorError::TimestampSourceError] - When a [TimestampSource] fails
+///   with an error of its own, e.g. through a [FallibleTimestamp].
 #[derive(Debug)]
 pub enum SnowflakeGeneratorError {
     SequenceOverflow,
     TimestampOverflow,
     TimestampError(&'static str),
     InvalidBitConfig,
+    WaitTimeout,
+    MachineIdOverflow,
+    CoreIdUnavailable,
+    FlavorOverflow,
+    TimestampBelowFloor,
+    #[cfg(feature = "mmap")]
+    StateFileError(std::io::Error),
+    MissingField(&'static str),
+    ClockWentBackwards,
+    InvalidEncoding,
+    EpochInFuture,
+    MachineIdUnavailable,
+    #[cfg(feature = "std")]
+    TimestampSourceError(Box<dyn std::error::Error + Send + Sync>),
+}
+
+impl core::fmt::Display for SnowflakeGeneratorError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::SequenceOverflow => write!(f, "sequence overflowed for the current millisecond"),
+            Self::TimestampOverflow => write!(f, "timestamp doesn't fit in the timestamp bits"),
+            Self::TimestampError(error) => write!(f, "failed to get timestamp: {error}"),
+            Self::InvalidBitConfig => write!(f, "invalid snowflake bit configuration"),
+            Self::WaitTimeout => write!(f, "timed out waiting for the clock to advance"),
+            Self::MachineIdOverflow => write!(f, "machine ID doesn't fit in the machine ID bits"),
+            Self::CoreIdUnavailable => write!(f, "current CPU core is unavailable"),
+            Self::FlavorOverflow => write!(f, "flavor doesn't fit in the flavor bits"),
+            Self::TimestampBelowFloor => write!(f, "timestamp is below the configured minimum"),
+            #[cfg(feature = "mmap")]
+            Self::StateFileError(error) => write!(f, "failed to set up state file: {error}"),
+            Self::MissingField(field) => write!(f, "required field {field} wasn't set"),
+            Self::ClockWentBackwards => write!(f, "clock went backwards"),
+            Self::InvalidEncoding => write!(f, "invalid snowflake encoding"),
+            Self::EpochInFuture => write!(f, "epoch is later than the current timestamp"),
+            Self::MachineIdUnavailable => write!(f, "no machine ID is available"),
+            #[cfg(feature = "std")]
+            Self::TimestampSourceError(error) => write!(f, "failed to get timestamp: {error}"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for SnowflakeGeneratorError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            #[cfg(feature = "mmap")]
+            Self::StateFileError(error) => Some(error),
+            Self::TimestampSourceError(error) => Some(error.as_ref()),
+            _ => None,
+        }
+    }
 }
 
 impl From<&'static str> for SnowflakeGeneratorError {
@@ -62,23 +248,57 @@ impl From<&'static str> for SnowflakeGeneratorError {
     }
 }
 
+#[cfg(feature = "std")]
+impl From<std::time::SystemTimeError> for SnowflakeGeneratorError {
+    fn from(error: std::time::SystemTimeError) -> Self {
+        Self::TimestampSourceError(Box::new(error))
+    }
+}
+
+/// The current time in milliseconds since the UNIX epoch.
+///
+/// This reads the system clock, and can be passed straight to a generator as its
+/// timestamp function.
+#[cfg(feature = "std")]
+pub fn unix_millis() -> Result<u64, &'static str> {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|now| now.as_millis() as u64)
+        .map_err(|_| "Clock is before the UNIX epoch")
+}
+
 /// A generator for creating unique snowflake IDs.
 ///
 /// The SnowFlakeGenerator is the main struct for creating snowflakes. It
 /// is responsible for generating unique IDs based on the current time and
-/// the machine ID.
-pub struct SnowflakeGenerator<T>
+/// the machine ID. Sequence IDs are handed out by a [SequenceAllocator], which
+/// defaults to the lock-free [TimestampSequenceGenerator].
+pub struct SnowflakeGenerator<T, S = TimestampSequenceGenerator>
 where
-    T: Fn() -> Result<u64, &'static str>,
+    T: TimestampSource,
+    S: SequenceAllocator,
 {
-    machine_id: u32,
-    ts_gen: TimestampSequenceGenerator,
+    machine_id: core::sync::atomic::AtomicU32,
+    sequence_allocator: S,
     epoch: u64,
+    display_epoch: u64,
+    start_timestamp: u64,
     get_timestamp: T,
     config: SnowflakeConfig,
+    #[cfg(feature = "alloc")]
+    sequence_pressure: Option<SequencePressure>,
+}
+
+/// A callback for sequences above a high-water mark, see
+/// [SnowflakeGenerator::on_sequence_pressure].
+#[cfg(feature = "alloc")]
+#[derive(Clone)]
+struct SequencePressure {
+    high_water: u64,
+    callback: alloc::sync::Arc<dyn Fn(u64, u64) + Send + Sync>,
 }
 
-impl<T: Fn() -> Result<u64, &'static str>> SnowflakeGenerator<T> {
+impl<T: TimestampSource> SnowflakeGenerator<T> {
     /// Create a new SnowFlakeGenerator with default configuration.
     ///
     /// This funcion creates a new SnowFlakeGenerator that creates snowflakes
@@ -101,27 +321,743 @@ impl<T: Fn() -> Result<u64, &'static str>> SnowflakeGenerator<T> {
         get_timestamp: T,
         config: SnowflakeConfig,
     ) -> Result<Self, SnowflakeGeneratorError> {
+        config.machine_id_policy.check(machine_id, &config)?;
         let timestamp_ms = Self::get_epoch_relative_timestamp(&get_timestamp, epoch, &config)?;
-        let ts_gen = TimestampSequenceGenerator::new(timestamp_ms, config);
-        Ok(Self {
+        let sequence_allocator = TimestampSequenceGenerator::new(timestamp_ms, config);
+        Ok(Self::from_parts(
             machine_id,
-            ts_gen,
+            timestamp_ms,
             epoch,
             get_timestamp,
             config,
-        })
+            sequence_allocator,
+        ))
+    }
+
+    /// Create a new SnowflakeGenerator that resumes after a saved state.
+    ///
+    /// `last_timestamp` and `last_sequence` are the epoch-relative timestamp and the
+    /// sequence of the last snowflake handed out, e.g. as returned by
+    /// [SnowflakeGenerator::generate_detailed]. The first snowflake generated
+    /// continues right after them, so a restart within the same millisecond can't
+    /// reissue an ID. If the clock has moved on since, generation picks up at the
+    /// clock as usual.
+    ///
+    /// This only holds if the state is persisted before the snowflake it belongs to
+    /// is used, and if the last saved state is the one restored. Restoring an older
+    /// state reopens the IDs issued after it. Returns
+    /// [SnowflakeGeneratorError::TimestampOverflow] or
+    /// [SnowflakeGeneratorError::SequenceOverflow] if the state doesn't fit in the
+    /// config.
+    pub fn new_with_state(
+        machine_id: u32,
+        epoch: u64,
+        get_timestamp: T,
+        config: SnowflakeConfig,
+        last_timestamp: u64,
+        last_sequence: u64,
+    ) -> Result<Self, SnowflakeGeneratorError> {
+        if last_timestamp >= config.timestamp_max {
+            return Err(SnowflakeGeneratorError::TimestampOverflow);
+        }
+        if last_sequence > config.sequence_max {
+            return Err(SnowflakeGeneratorError::SequenceOverflow);
+        }
+        config.machine_id_policy.check(machine_id, &config)?;
+        let timestamp_ms = Self::get_epoch_relative_timestamp(&get_timestamp, epoch, &config)?;
+        let sequence_allocator =
+            TimestampSequenceGenerator::resume(last_timestamp, last_sequence, config);
+        Ok(Self::from_parts(
+            machine_id,
+            timestamp_ms,
+            epoch,
+            get_timestamp,
+            config,
+            sequence_allocator,
+        ))
+    }
+
+    /// Create a new SnowflakeGenerator from a datacenter and a worker ID.
+    ///
+    /// The config must split the machine ID with
+    /// [SnowflakeConfig::with_datacenter_bits]. The two IDs are combined with
+    /// [SnowflakeConfig::machine_id_from_parts], and otherwise this is the same as
+    /// [SnowflakeGenerator::new_with_config].
+    pub fn new_with_datacenter(
+        datacenter_id: u32,
+        worker_id: u32,
+        epoch: u64,
+        get_timestamp: T,
+        config: SnowflakeConfig,
+    ) -> Result<Self, SnowflakeGeneratorError> {
+        let machine_id = config.machine_id_from_parts(datacenter_id, worker_id)?;
+        Self::new_with_config(machine_id, epoch, get_timestamp, config)
+    }
+
+    /// Create a new SnowflakeGenerator, retrying the timestamp function if it fails.
+    ///
+    /// Similar to [SnowflakeGenerator::new_with_config], but if the timestamp function
+    /// returns an error it is retried up to `retries` more times. The delay between
+    /// attempts starts at `backoff` and doubles after every failure. Both
+    /// [SnowflakeGeneratorError::TimestampError] and
+    /// [SnowflakeGeneratorError::TimestampSourceError] are retried, and if every
+    /// attempt fails the last of them is returned.
+    #[cfg(feature = "std")]
+    pub fn new_with_retries(
+        machine_id: u32,
+        epoch: u64,
+        get_timestamp: T,
+        config: SnowflakeConfig,
+        retries: u32,
+        backoff: std::time::Duration,
+    ) -> Result<Self, SnowflakeGeneratorError> {
+        config.machine_id_policy.check(machine_id, &config)?;
+
+        let mut delay = backoff;
+        let mut attempt = 0;
+        let timestamp_ms = loop {
+            match Self::get_epoch_relative_timestamp(&get_timestamp, epoch, &config) {
+                Err(
+                    SnowflakeGeneratorError::TimestampError(_)
+                    | SnowflakeGeneratorError::TimestampSourceError(_),
+                ) if attempt < retries => {
+                    std::thread::sleep(delay);
+                    delay = delay.saturating_mul(2);
+                    attempt += 1;
+                }
+                result => break result?,
+            }
+        };
+
+        let sequence_allocator = TimestampSequenceGenerator::new(timestamp_ms, config);
+        Ok(Self::from_parts(
+            machine_id,
+            timestamp_ms,
+            epoch,
+            get_timestamp,
+            config,
+            sequence_allocator,
+        ))
+    }
+
+    /// Move the generator forward to a future timestamp.
+    ///
+    /// This is meant for load tests that replay future-dated events. The timestamp
+    /// is in the same units as the timestamp function, and must fit in the config.
+    /// Until the clock catches up, snowflakes are generated with this timestamp and
+    /// an increasing sequence, so they are future-dated and the sequence will
+    /// overflow once the millisecond is used up. Prewarming to a timestamp the
+    /// generator has already reached has no effect.
+    pub fn prewarm(&self, timestamp_ms: u64) -> Result<(), SnowflakeGeneratorError> {
+        let timestamp_ms = self.config.epoch_relative(timestamp_ms, self.epoch)?;
+        self.sequence_allocator.advance_to(timestamp_ms);
+        Ok(())
+    }
+
+    /// The number of snowflakes that can still be generated in the current millisecond.
+    ///
+    /// Once this reaches 0, [SnowflakeGenerator::generate] returns
+    /// [SnowflakeGeneratorError::SequenceOverflow] (or waits, under
+    /// [OverflowPolicy::SpinWait]) until the clock moves on. If the clock has already
+    /// moved past the last snowflake generated, the whole sequence of
+    /// `sequence_max + 1` IDs is available again. This is a single atomic load plus
+    /// a clock read, so it can be polled to throttle before hitting overflows. If
+    /// the clock read fails, the count for the last millisecond is returned.
+    pub fn sequence_remaining(&self) -> u64 {
+        let timestamp =
+            Self::get_epoch_relative_timestamp(&self.get_timestamp, self.epoch, &self.config)
+                .unwrap_or(0);
+        self.sequence_allocator.sequence_remaining(timestamp)
+    }
+
+    /// A snapshot of the timestamp and sequence the generator is currently on.
+    ///
+    /// The timestamp is epoch-relative, and the sequence is the number of snowflakes
+    /// generated at it so far, so it reaches `sequence_max + 1` once the millisecond
+    /// is used up. This is a single atomic load that neither reads the clock nor
+    /// changes any state, which makes it safe to expose for diagnostics. The
+    /// timestamp only moves when a snowflake is generated, so it can lag the clock.
+    pub fn state(&self) -> (u64, u64) {
+        self.sequence_allocator.state()
+    }
+
+    /// Generate a batch of `n` consecutive snowflakes.
+    ///
+    /// The clock is read once, and the whole run of sequence IDs is reserved with a
+    /// single atomic update. If the run doesn't fit in the rest of the current
+    /// millisecond it carries on into the following ones, so the tail of a large
+    /// batch is dated ahead of the clock, like after [SnowflakeGenerator::prewarm].
+    /// The snowflakes are strictly increasing, and later calls continue after the
+    /// batch. Returns [SnowflakeGeneratorError::TimestampOverflow] if the run would
+    /// go past the last timestamp.
+    #[cfg(feature = "alloc")]
+    pub fn generate_batch(&self, n: usize) -> Result<Vec<u64>, SnowflakeGeneratorError> {
+        if n == 0 {
+            return Ok(Vec::new());
+        }
+
+        let timestamp =
+            Self::get_epoch_relative_timestamp(&self.get_timestamp, self.epoch, &self.config)?;
+        let first = self.sequence_allocator.reserve(timestamp, n as u64)?;
+
+        let per_timestamp = self.config.sequence_max + 1;
+        let machine_id = self.machine_id() as u64;
+        Ok((first.sequence..first.sequence + n as u64)
+            .map(|position| {
+                self.config.pack(
+                    first.timestamp + position / per_timestamp,
+                    machine_id,
+                    position % per_timestamp,
+                )
+            })
+            .collect())
+    }
+}
+
+impl<T, S> SnowflakeGenerator<T, S>
+where
+    T: TimestampSource,
+    S: SequenceAllocator,
+{
+    /// Create a new SnowflakeGenerator with a custom sequence allocator.
+    ///
+    /// Similar to [SnowflakeGenerator::new_with_config], but sequence IDs are taken
+    /// from the given allocator rather than the built-in atomic one.
+    pub fn new_with_allocator(
+        machine_id: u32,
+        epoch: u64,
+        get_timestamp: T,
+        config: SnowflakeConfig,
+        sequence_allocator: S,
+    ) -> Result<Self, SnowflakeGeneratorError> {
+        config.machine_id_policy.check(machine_id, &config)?;
+        let timestamp_ms = Self::get_epoch_relative_timestamp(&get_timestamp, epoch, &config)?;
+        Ok(Self::from_parts(
+            machine_id,
+            timestamp_ms,
+            epoch,
+            get_timestamp,
+            config,
+            sequence_allocator,
+        ))
+    }
+
+    fn from_parts(
+        machine_id: u32,
+        timestamp_ms: u64,
+        epoch: u64,
+        get_timestamp: T,
+        config: SnowflakeConfig,
+        sequence_allocator: S,
+    ) -> Self {
+        Self {
+            machine_id: core::sync::atomic::AtomicU32::new(machine_id),
+            sequence_allocator,
+            epoch,
+            display_epoch: epoch,
+            start_timestamp: timestamp_ms,
+            get_timestamp,
+            config,
+            #[cfg(feature = "alloc")]
+            sequence_pressure: None,
+        }
     }
 
     /// Generate a new snowflake.
     ///
     /// This function generates a new snowflake ID. If the sequence overflows,
-    /// it will return [SnowFlakeGeneratorError::SequenceOverflow].
+    /// it will return [SnowFlakeGeneratorError::SequenceOverflow], unless the config
+    /// uses [OverflowPolicy::SpinWait], in which case it waits for the next
+    /// millisecond instead.
     pub fn generate(&self) -> Result<u64, SnowflakeGeneratorError> {
-        let new_timestamp =
-            Self::get_epoch_relative_timestamp(&self.get_timestamp, self.epoch, &self.config)?;
-        let timestamp_sequence = self.ts_gen.increment_sequence(new_timestamp)?;
+        self.generate_with_policy(self.config.overflow_policy)
+    }
+
+    /// Generate a new snowflake without checking the timestamp against the config.
+    ///
+    /// This behaves like [SnowflakeGenerator::generate], sequence overflows included,
+    /// but skips checking that the timestamp is within
+    /// [SnowflakeConfig::timestamp_max] and the minimum timestamp. It is only meant
+    /// for hot paths where the epoch is known to outlive the process. A clock before
+    /// the epoch is still reported as [SnowflakeGeneratorError::EpochInFuture].
+    ///
+    /// Past the limit, the timestamp is truncated to its bits and starts over from
+    /// the epoch. To the generator that is the clock going backwards, handled by the
+    /// [ClockBackwardsPolicy] of the config: under [ClockBackwardsPolicy::Lenient],
+    /// snowflakes keep counting up the sequence of the last timestamp generated, so
+    /// they stay unique, until it runs out and
+    /// [SnowflakeGeneratorError::SequenceOverflow] is returned for good. Under
+    /// [OverflowPolicy::SpinWait] that is
+    /// [SnowflakeGeneratorError::TimestampOverflow] instead, as the clock can't catch
+    /// up. Under [ClockBackwardsPolicy::Reject], generation fails with
+    /// [SnowflakeGeneratorError::ClockWentBackwards].
+    pub fn generate_unchecked(&self) -> Result<u64, SnowflakeGeneratorError> {
+        loop {
+            let timestamp = self
+                .get_timestamp
+                .timestamp()?
+                .checked_sub(self.epoch)
+                .ok_or(SnowflakeGeneratorError::EpochInFuture)?
+                & self.config.timestamp_mask;
+            match self.generate_at(timestamp) {
+                Err(SnowflakeGeneratorError::SequenceOverflow)
+                    if self.config.overflow_policy == OverflowPolicy::SpinWait =>
+                {
+                    self.spin_until_after(timestamp)?
+                }
+                result => return result.map(|generated| generated.id),
+            }
+        }
+    }
+
+    /// Generate a new snowflake, failing right away if the sequence overflows.
+    ///
+    /// This behaves like [SnowflakeGenerator::generate] under [OverflowPolicy::Error],
+    /// whatever the policy of the config.
+    pub fn try_generate(&self) -> Result<u64, SnowflakeGeneratorError> {
+        self.generate_with_policy(OverflowPolicy::Error)
+    }
+
+    /// Generate a new snowflake, waiting for the next millisecond if the sequence
+    /// overflows.
+    ///
+    /// This behaves like [SnowflakeGenerator::generate] under
+    /// [OverflowPolicy::SpinWait], whatever the policy of the config. The timestamp
+    /// function is polled in a busy loop while waiting, and any error it returns,
+    /// as well as [SnowflakeGeneratorError::TimestampOverflow], is passed on as is.
+    pub fn generate_blocking(&self) -> Result<u64, SnowflakeGeneratorError> {
+        self.generate_with_policy(OverflowPolicy::SpinWait)
+    }
+
+    /// Generate a new snowflake, wrapped in a [Snowflake].
+    ///
+    /// This is the same as [SnowflakeGenerator::generate], but the snowflake can't be
+    /// mixed up with other integers.
+    pub fn generate_typed(&self) -> Result<Snowflake, SnowflakeGeneratorError> {
+        self.generate().map(Snowflake::from)
+    }
+
+    /// Generate a new snowflake as a fixed-width hex string.
+    ///
+    /// See [encode_hex] for the format.
+    #[cfg(feature = "alloc")]
+    pub fn generate_hex(&self) -> Result<alloc::string::String, SnowflakeGeneratorError> {
+        Ok(encode_hex(self.generate()?, &self.config))
+    }
+
+    /// Generate a new snowflake as a decimal string.
+    #[cfg(feature = "alloc")]
+    pub fn generate_decimal(&self) -> Result<alloc::string::String, SnowflakeGeneratorError> {
+        Ok(alloc::string::ToString::to_string(&self.generate()?))
+    }
+
+    /// Generate a new snowflake, along with the fields it was packed from.
+    ///
+    /// This is the same as [SnowflakeGenerator::generate], but the timestamp and
+    /// sequence actually used are handed back too, e.g. for logging, without having
+    /// to decode the snowflake again. The timestamp is epoch-relative, and may be
+    /// later than the clock read, see [ClockBackwardsPolicy::Lenient].
+    pub fn generate_detailed(&self) -> Result<GeneratedSnowflake, SnowflakeGeneratorError> {
+        self.generate_detailed_with_policy(self.config.overflow_policy)
+    }
+
+    /// Generate a new snowflake as a non-negative `i64`.
+    ///
+    /// This is the same as [SnowflakeGenerator::generate], for storage that only
+    /// has signed 64-bit integers. The config is checked on every call, not when the
+    /// generator is created: a generator whose config uses the top bit constructs
+    /// fine, and only this method returns [SnowflakeGeneratorError::InvalidBitConfig],
+    /// see [SnowflakeConfig::is_i64_safe]. To fail at construction instead, build
+    /// the config with [SnowflakeConfig::i64_safe].
+    pub fn generate_i64(&self) -> Result<i64, SnowflakeGeneratorError> {
+        if !self.config.is_i64_safe() {
+            return Err(SnowflakeGeneratorError::InvalidBitConfig);
+        }
+
+        self.generate().map(|snowflake| snowflake as i64)
+    }
+
+    /// Generate a new snowflake tagged with the given flavor.
+    ///
+    /// Returns [SnowflakeGeneratorError::FlavorOverflow] if the flavor doesn't fit in
+    /// the flavor bits of the config, see [SnowflakeConfig::with_flavor_bits].
+    pub fn generate_with_flavor(&self, flavor: u8) -> Result<u64, SnowflakeGeneratorError> {
+        if flavor as u64 > build_mask(self.config.flavor_bits) {
+            return Err(SnowflakeGeneratorError::FlavorOverflow);
+        }
+
+        let snowflake = self.generate()?;
+        Ok(snowflake | (flavor as u64) << self.config.flavor_shift())
+    }
+
+    /// Turn this generator into one that can be cloned.
+    ///
+    /// The sequence allocator is moved behind an [Arc](alloc::sync::Arc), and every
+    /// clone of the returned generator shares it. Together the clones then hand out
+    /// snowflakes exactly like this generator would on its own, so no two of them
+    /// ever produce the same snowflake, from whichever thread they are used. This
+    /// holds as long as every clone's timestamp source reads the same clock, and none
+    /// of them is moved to a different machine ID with
+    /// [SnowflakeGenerator::rotate_machine_id], which only affects the generator it
+    /// is called on.
+    ///
+    /// ```rust
+    /// use frostbit::SnowflakeGenerator;
+    ///
+    /// let gen = SnowflakeGenerator::new(0, 0, || Ok(0x1234)).unwrap().into_shared();
+    /// let clone = gen.clone();
+    /// assert_ne!(gen.generate().unwrap(), clone.generate().unwrap());
+    /// ```
+    #[cfg(feature = "alloc")]
+    pub fn into_shared(self) -> SnowflakeGenerator<T, alloc::sync::Arc<S>> {
+        SnowflakeGenerator {
+            machine_id: self.machine_id,
+            sequence_allocator: alloc::sync::Arc::new(self.sequence_allocator),
+            epoch: self.epoch,
+            display_epoch: self.display_epoch,
+            start_timestamp: self.start_timestamp,
+            get_timestamp: self.get_timestamp,
+            config: self.config,
+            sequence_pressure: self.sequence_pressure,
+        }
+    }
+
+    /// Create a generator with the same machine ID, epoch, and config, but a new clock.
+    ///
+    /// This is handy for mirroring a production generator under a controllable clock
+    /// in tests. The new generator starts with fresh sequence state from the default
+    /// allocator, so it shares nothing with this one. The new clock is read once to
+    /// seed that state, which is why this can fail.
+    pub fn with_clock<C>(
+        &self,
+        new_clock: C,
+    ) -> Result<SnowflakeGenerator<C>, SnowflakeGeneratorError>
+    where
+        C: TimestampSource,
+    {
+        let generator = SnowflakeGenerator::new_with_config(
+            self.machine_id(),
+            self.epoch,
+            new_clock,
+            self.config,
+        )?;
+        Ok(generator.with_display_epoch(self.display_epoch))
+    }
+
+    /// Set the zero point that decoded timestamps are reported relative to.
+    ///
+    /// The epoch is subtracted from timestamps to save bits when packing, and may
+    /// well differ from the point in time a product counts from, such as its launch.
+    /// The display epoch only affects [SnowflakeGenerator::display_timestamp], the
+    /// snowflakes themselves are unchanged. It defaults to the epoch.
+    pub fn with_display_epoch(mut self, display_epoch: u64) -> Self {
+        self.display_epoch = display_epoch;
+        self
+    }
+
+    /// Call `callback` whenever a snowflake is generated with a sequence that exceeds
+    /// the high-water mark.
+    ///
+    /// The high-water mark is a fraction of [SnowflakeConfig::sequence_max], as in
+    /// [SnowflakeGenerator::generate_with_saturation]. It is clamped to `0.0..=1.0`,
+    /// and NaN counts as 1.0, so a mark of 1.0 never fires. The callback gets the
+    /// sequence of the snowflake and the maximum. It is called from within
+    /// `generate`, on the generating thread, for every snowflake past the mark, so it
+    /// should be cheap, like bumping a metric. Without a callback, generating only
+    /// pays for checking that there is none.
+    #[cfg(feature = "alloc")]
+    pub fn on_sequence_pressure<F>(mut self, high_water: f32, callback: F) -> Self
+    where
+        F: Fn(u64, u64) + Send + Sync + 'static,
+    {
+        let fraction = if high_water.is_nan() {
+            1.0
+        } else {
+            high_water.clamp(0.0, 1.0)
+        };
+        // a whole sequence exceeds the mark exactly if it exceeds the mark rounded down
+        self.sequence_pressure = Some(SequencePressure {
+            high_water: (fraction * self.config.sequence_max as f32) as u64,
+            callback: alloc::sync::Arc::new(callback),
+        });
+        self
+    }
+
+    /// The timestamp of a snowflake relative to the display epoch, in milliseconds.
+    ///
+    /// Like the epoch, the display epoch is in the units of the timestamp function.
+    /// The difference is taken in those ticks and then converted with the
+    /// [TimeUnit] of the config, rounding towards zero. This is negative for
+    /// snowflakes from before the display epoch.
+    pub fn display_timestamp(&self, id: u64) -> i64 {
+        let ticks = self.config.timestamp_of(id) + self.epoch;
+        let time_unit = self.config.time_unit;
+        if ticks >= self.display_epoch {
+            time_unit
+                .to_duration(ticks - self.display_epoch)
+                .as_millis() as i64
+        } else {
+            -(time_unit
+                .to_duration(self.display_epoch - ticks)
+                .as_millis() as i64)
+        }
+    }
+
+    /// The absolute timestamp of a snowflake in milliseconds.
+    ///
+    /// This is the timestamp field of the snowflake with the epoch added back, i.e.
+    /// the value the timestamp function returned when the snowflake was generated,
+    /// converted from the [TimeUnit] of the config to milliseconds.
+    pub fn decode_timestamp_ms(&self, snowflake: u64) -> u64 {
+        self.decode_timestamp(snowflake).as_millis() as u64
+    }
+
+    /// The absolute timestamp of a snowflake, as a duration since the zero of the
+    /// timestamp function.
+    ///
+    /// For a timestamp function like [unix_millis] this is the time since the UNIX
+    /// epoch. The ticks of the timestamp field are converted with the [TimeUnit] of
+    /// the config.
+    pub fn decode_timestamp(&self, snowflake: u64) -> core::time::Duration {
+        let ticks = self.config.timestamp_of(snowflake) + self.epoch;
+        self.config.time_unit.to_duration(ticks)
+    }
+
+    /// The absolute time in milliseconds from which the timestamp field overflows.
+    ///
+    /// From then on, generation fails with [SnowflakeGeneratorError::TimestampOverflow].
+    /// This is the epoch plus [SnowflakeConfig::timestamp_max], converted from the
+    /// [TimeUnit] of the config to milliseconds.
+    pub fn overflow_instant_ms(&self) -> u64 {
+        self.overflow_instant().as_millis() as u64
+    }
+
+    /// The point in time from which the timestamp field overflows.
+    ///
+    /// Like [SnowflakeGenerator::overflow_instant_ms], assuming the timestamp function
+    /// counts from the UNIX epoch. Returns `None` if the instant is too far out for
+    /// a [std::time::SystemTime] on this platform, whose range differs between
+    /// operating systems.
+    #[cfg(feature = "std")]
+    pub fn overflow_systemtime(&self) -> Option<std::time::SystemTime> {
+        std::time::UNIX_EPOCH.checked_add(self.overflow_instant())
+    }
+
+    fn overflow_instant(&self) -> core::time::Duration {
+        let ticks = self.epoch.saturating_add(self.config.timestamp_max);
+        self.config.time_unit.to_duration(ticks)
+    }
+
+    /// Generate a new snowflake that isn't in the given set of existing snowflakes.
+    ///
+    /// Meant for repair tools that backfill IDs next to ones that are already issued.
+    /// Snowflakes found in the set are skipped, moving on through the sequence, and
+    /// on to the next millisecond when the clock advances. If the sequence overflows
+    /// first, [SnowflakeGeneratorError::SequenceOverflow] is returned.
+    #[cfg(feature = "std")]
+    pub fn generate_avoiding(
+        &self,
+        existing: &std::collections::HashSet<u64>,
+    ) -> Result<u64, SnowflakeGeneratorError> {
+        loop {
+            let snowflake = self.generate()?;
+            if !existing.contains(&snowflake) {
+                return Ok(snowflake);
+            }
+        }
+    }
+
+    /// Generate a new snowflake along with a process-wide ordinal.
+    ///
+    /// The ordinal comes from a single counter shared by every generator in the
+    /// process. It only counts successful generations and never resets, unlike the
+    /// sequence which starts over every millisecond, so it forms a dense local
+    /// sequence that can be cross-referenced with an event log. It is not persisted,
+    /// and starts from 0 again when the process restarts.
+    pub fn generate_with_ordinal(&self) -> Result<(u64, u64), SnowflakeGeneratorError> {
+        let snowflake = self.generate()?;
+        let ordinal = ORDINAL.fetch_add(1, core::sync::atomic::Ordering::Relaxed);
+        Ok((snowflake, ordinal))
+    }
+
+    /// Generate a new snowflake along with how full its millisecond was.
+    ///
+    /// The saturation is `sequence / sequence_max` at the time the snowflake was
+    /// issued, from 0.0 for the first snowflake of a millisecond up to 1.0 for the
+    /// last one before the sequence overflows. Sampling it gives a continuous signal
+    /// for alerting or autoscaling, rather than only finding out on overflow.
+    pub fn generate_with_saturation(&self) -> Result<(u64, f32), SnowflakeGeneratorError> {
+        let snowflake = self.generate()?;
+        let sequence = self.config.sequence_of(snowflake);
+        Ok((snowflake, sequence as f32 / self.config.sequence_max as f32))
+    }
+
+    /// Switch the generator over to a new machine ID.
+    ///
+    /// Meant for processes that lease machine IDs from a coordinator and rotate
+    /// through them. The switch is atomic, and snowflakes generated concurrently
+    /// carry either the old or the new machine ID. The timestamp and sequence keep
+    /// counting across the switch, so snowflakes from before and after it can't
+    /// collide with each other, even if the new machine ID is the same as the old
+    /// one. What rotation can't protect against is another generator still using
+    /// the new machine ID. After a rotation, [SnowflakeGenerator::could_have_produced]
+    /// only accepts the new machine ID.
+    ///
+    /// The new machine ID is checked against the machine ID policy of the config.
+    pub fn rotate_machine_id(&self, new_id: u32) -> Result<(), SnowflakeGeneratorError> {
+        self.config.machine_id_policy.check(new_id, &self.config)?;
+        self.machine_id
+            .store(new_id, core::sync::atomic::Ordering::Relaxed);
+        Ok(())
+    }
 
-        Ok(timestamp_sequence.into_snowflake(self.machine_id as u64, &self.config))
+    /// Check whether a snowflake could plausibly have come from this generator.
+    ///
+    /// The snowflake must carry this generator's machine ID, must not be older than
+    /// the generator itself, and must not set any bits outside of the config. This is
+    /// only a heuristic, another generator with the same machine ID would pass too.
+    pub fn could_have_produced(&self, id: u64) -> bool {
+        let machine_id = self.machine_id() as u64 & self.config.machine_id_mask;
+        self.config.fits(id)
+            && self.config.machine_id_of(id) == machine_id
+            && self.config.timestamp_of(id) >= self.start_timestamp
+    }
+
+    /// Generate a new snowflake, waiting for the clock if needed until the deadline.
+    ///
+    /// This behaves like [SnowflakeGenerator::generate], except that if the sequence
+    /// overflows it polls the timestamp function until the next millisecond instead
+    /// of failing. If the deadline passes before the clock advances,
+    /// [SnowflakeGeneratorError::WaitTimeout] is returned.
+    #[cfg(feature = "std")]
+    pub fn generate_by(
+        &self,
+        deadline: std::time::Instant,
+    ) -> Result<u64, SnowflakeGeneratorError> {
+        loop {
+            let timestamp =
+                Self::get_epoch_relative_timestamp(&self.get_timestamp, self.epoch, &self.config)?;
+            match self.generate_at(timestamp) {
+                Err(SnowflakeGeneratorError::SequenceOverflow) => {
+                    self.wait_for_timestamp_after(timestamp, deadline)?
+                }
+                result => return result.map(|generated| generated.id),
+            }
+        }
+    }
+
+    /// Measure how many snowflakes this generator can produce in the given time.
+    ///
+    /// Snowflakes are generated in a loop against the real timestamp function until
+    /// the duration elapses, and the number generated is returned. When the sequence
+    /// overflows, the loop waits for the clock to advance rather than retrying. Once
+    /// done, it waits up to another `duration` for the clock to move past the last
+    /// millisecond used, so the generator isn't left with an exhausted sequence.
+    /// The snowflakes are thrown away, so this burns through IDs and is meant to be
+    /// run once at startup. Measuring stops early if the timestamp function fails.
+    #[cfg(feature = "std")]
+    pub fn measure_throughput(&self, duration: std::time::Duration) -> u64 {
+        let end = std::time::Instant::now() + duration;
+        let mut count = 0;
+        let mut last_timestamp = None;
+
+        while std::time::Instant::now() < end {
+            let Ok(timestamp) =
+                Self::get_epoch_relative_timestamp(&self.get_timestamp, self.epoch, &self.config)
+            else {
+                break;
+            };
+
+            last_timestamp = Some(timestamp);
+            match self.generate_at(timestamp) {
+                Ok(_) => count += 1,
+                Err(SnowflakeGeneratorError::SequenceOverflow) => {
+                    if self.wait_for_timestamp_after(timestamp, end).is_err() {
+                        break;
+                    }
+                }
+                Err(_) => break,
+            }
+        }
+
+        if let Some(timestamp) = last_timestamp {
+            let deadline = std::time::Instant::now() + duration;
+            let _ = self.wait_for_timestamp_after(timestamp, deadline);
+        }
+        count
+    }
+
+    fn generate_with_policy(&self, policy: OverflowPolicy) -> Result<u64, SnowflakeGeneratorError> {
+        Ok(self.generate_detailed_with_policy(policy)?.id)
+    }
+
+    fn generate_detailed_with_policy(
+        &self,
+        policy: OverflowPolicy,
+    ) -> Result<GeneratedSnowflake, SnowflakeGeneratorError> {
+        loop {
+            let new_timestamp =
+                Self::get_epoch_relative_timestamp(&self.get_timestamp, self.epoch, &self.config)?;
+            match self.generate_at(new_timestamp) {
+                Err(SnowflakeGeneratorError::SequenceOverflow)
+                    if policy == OverflowPolicy::SpinWait =>
+                {
+                    self.spin_until_after(new_timestamp)?
+                }
+                result => return result,
+            }
+        }
+    }
+
+    fn generate_at(&self, timestamp: u64) -> Result<GeneratedSnowflake, SnowflakeGeneratorError> {
+        let timestamp_sequence = self.sequence_allocator.allocate(timestamp)?;
+        #[cfg(feature = "alloc")]
+        if let Some(pressure) = &self.sequence_pressure {
+            if timestamp_sequence.sequence > pressure.high_water {
+                (pressure.callback)(timestamp_sequence.sequence, self.config.sequence_max);
+            }
+        }
+
+        let machine_id = self.machine_id() as u64;
+        Ok(GeneratedSnowflake {
+            id: timestamp_sequence.into_snowflake(machine_id, &self.config),
+            timestamp: timestamp_sequence.timestamp,
+            machine_id,
+            sequence: timestamp_sequence.sequence,
+        })
+    }
+
+    fn machine_id(&self) -> u32 {
+        self.machine_id.load(core::sync::atomic::Ordering::Relaxed)
+    }
+
+    fn spin_until_after(&self, last_timestamp: u64) -> Result<(), SnowflakeGeneratorError> {
+        while Self::get_epoch_relative_timestamp(&self.get_timestamp, self.epoch, &self.config)?
+            <= last_timestamp
+        {
+            core::hint::spin_loop();
+        }
+        Ok(())
+    }
+
+    #[cfg(feature = "std")]
+    fn wait_for_timestamp_after(
+        &self,
+        last_timestamp: u64,
+        deadline: std::time::Instant,
+    ) -> Result<(), SnowflakeGeneratorError> {
+        loop {
+            let timestamp =
+                Self::get_epoch_relative_timestamp(&self.get_timestamp, self.epoch, &self.config)?;
+            if timestamp > last_timestamp {
+                return Ok(());
+            }
+
+            if std::time::Instant::now() >= deadline {
+                return Err(SnowflakeGeneratorError::WaitTimeout);
+            }
+            std::hint::spin_loop();
+        }
     }
 
     fn get_epoch_relative_timestamp(
@@ -129,11 +1065,30 @@ impl<T: Fn() -> Result<u64, &'static str>> SnowflakeGenerator<T> {
         epoch: u64,
         config: &SnowflakeConfig,
     ) -> Result<u64, SnowflakeGeneratorError> {
-        let timestamp_ms = get_timestamp()? - epoch;
-        if timestamp_ms < config.timestamp_max {
-            Ok(timestamp_ms)
-        } else {
-            Err(SnowflakeGeneratorError::TimestampOverflow)
+        config.epoch_relative(get_timestamp.timestamp()?, epoch)
+    }
+}
+
+/// Clones share sequence state only if the allocator itself is shared, as with
+/// [SnowflakeGenerator::into_shared]. The built-in [TimestampSequenceGenerator] is
+/// deliberately not [Clone], since two copies of it under the same machine ID would
+/// hand out the same snowflakes.
+impl<T, S> Clone for SnowflakeGenerator<T, S>
+where
+    T: TimestampSource + Clone,
+    S: SequenceAllocator + Clone,
+{
+    fn clone(&self) -> Self {
+        Self {
+            machine_id: core::sync::atomic::AtomicU32::new(self.machine_id()),
+            sequence_allocator: self.sequence_allocator.clone(),
+            epoch: self.epoch,
+            display_epoch: self.display_epoch,
+            start_timestamp: self.start_timestamp,
+            get_timestamp: self.get_timestamp.clone(),
+            config: self.config,
+            #[cfg(feature = "alloc")]
+            sequence_pressure: self.sequence_pressure.clone(),
         }
     }
 }
@@ -142,8 +1097,24 @@ impl<T: Fn() -> Result<u64, &'static str>> SnowflakeGenerator<T> {
 ///
 /// The SnowFlakeConfig struct is used to define the configuration for a snowflake generator.
 /// It defines the number of bits used for the timestamp, machine ID, and sequence ID.
-#[derive(Debug, Clone, Copy)]
+///
+/// With the `serde` feature the config can be serialized, e.g. to load it from a
+/// config file. Only the settings it was built from are stored, and loading an
+/// invalid config fails.
+///
+/// Configs compare equal when they were built from the same settings, in which
+/// case they produce the same snowflakes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(
+    feature = "serde",
+    serde(
+        try_from = "serialize::SnowflakeConfigRepr",
+        into = "serialize::SnowflakeConfigRepr"
+    )
+)]
 pub struct SnowflakeConfig {
+    timestamp_bits: u64,
     machine_id_bits: u64,
     sequence_bits: u64,
     timestamp_mask: u64,
@@ -151,6 +1122,22 @@ pub struct SnowflakeConfig {
     sequence_mask: u64,
     timestamp_max: u64,
     sequence_max: u64,
+    era_bits: u64,
+    datacenter_bits: u64,
+    flavor_bits: u64,
+    tombstone_bits: u64,
+    prefix_bits: u64,
+    prefix_value: u64,
+    reserved_bits: u64,
+    machine_id_policy: MachineIdPolicy,
+    overflow_policy: OverflowPolicy,
+    clock_backwards_policy: ClockBackwardsPolicy,
+    atomic_ordering: AtomicOrdering,
+    time_unit: TimeUnit,
+    field_order: FieldOrder,
+    min_timestamp_ms: u64,
+    nonce_bits: u64,
+    hmac_bits: u64,
 }
 
 impl SnowflakeConfig {
@@ -161,7 +1148,39 @@ impl SnowflakeConfig {
         sequence_bits: u64,
     ) -> Result<Self, SnowflakeGeneratorError> {
         Self::validate_config(machine_id_bits, sequence_bits, timestamp_bits)?;
+        if machine_id_bits < OVERFLOW_GUARD_BITS {
+            // the overflow guard of the sequence lives where the machine ID is packed
+            return Err(SnowflakeGeneratorError::InvalidBitConfig);
+        }
+
+        Ok(Self::new_unchecked(
+            timestamp_bits,
+            machine_id_bits,
+            sequence_bits,
+        ))
+    }
 
+    /// Create a new [SnowflakeConfig] without checking the number of bits.
+    ///
+    /// Unlike [SnowflakeConfig::new] this is a `const fn`, so the config can be a
+    /// `const` or `static`:
+    ///
+    /// ```rust
+    /// use frostbit::SnowflakeConfig;
+    ///
+    /// const CONFIG: SnowflakeConfig = SnowflakeConfig::new_unchecked(41, 10, 12);
+    /// assert_eq!(CONFIG.sequence_max(), 4095);
+    /// ```
+    ///
+    /// It is up to the caller to pass bit counts that [SnowflakeConfig::new] would
+    /// accept: none of them zero, at least one machine ID bit, and at most 64 bits in
+    /// total. Otherwise building the config may fail to compile in a const context,
+    /// or panic, and generators may panic or hand out overlapping snowflakes.
+    pub const fn new_unchecked(
+        timestamp_bits: u64,
+        machine_id_bits: u64,
+        sequence_bits: u64,
+    ) -> Self {
         let timestamp_mask = build_mask(timestamp_bits);
         let machine_id_mask = build_mask(machine_id_bits);
         let sequence_mask = build_mask(sequence_bits);
@@ -169,7 +1188,8 @@ impl SnowflakeConfig {
         let timestamp_max = calc_max(timestamp_bits);
         let sequence_max = calc_max(sequence_bits);
 
-        Ok(Self {
+        Self {
+            timestamp_bits,
             machine_id_bits,
             sequence_bits,
             timestamp_mask,
@@ -177,136 +1197,2639 @@ impl SnowflakeConfig {
             sequence_mask,
             timestamp_max,
             sequence_max,
-        })
+            era_bits: 0,
+            datacenter_bits: 0,
+            flavor_bits: 0,
+            tombstone_bits: 0,
+            prefix_bits: 0,
+            prefix_value: 0,
+            reserved_bits: 0,
+            machine_id_policy: MachineIdPolicy::Reject,
+            overflow_policy: OverflowPolicy::Error,
+            clock_backwards_policy: ClockBackwardsPolicy::Lenient,
+            atomic_ordering: AtomicOrdering::SeqCst,
+            time_unit: TimeUnit::Millis,
+            field_order: FieldOrder::TimestampMachineSequence,
+            min_timestamp_ms: 0,
+            nonce_bits: 0,
+            hmac_bits: 0,
+        }
+    }
+
+    /// Create a [SnowflakeConfig] for Discord snowflakes.
+    ///
+    /// Discord uses 42 timestamp bits, 5 worker ID bits, 5 process ID bits, and 12
+    /// sequence bits, counting from the Discord epoch of `1420070400000`. The worker
+    /// and process IDs sit next to each other, so together they make up a 10 bit
+    /// machine ID of `worker_id << 5 | process_id`.
+    pub fn discord() -> Self {
+        Self::new(42, 10, 12).expect("Discord values incorrect")
+    }
+
+    /// Create a [SnowflakeConfig] for Instagram IDs.
+    ///
+    /// Instagram uses 41 timestamp bits, 13 shard ID bits, and 10 sequence bits. The
+    /// shard ID goes in the machine ID.
+    pub fn instagram() -> Self {
+        Self::new(41, 13, 10).expect("Instagram values incorrect")
+    }
+
+    /// Create a new [SnowflakeConfig] whose snowflakes are safe JavaScript integers.
+    ///
+    /// JavaScript numbers only represent integers exactly up to `2^53 - 1`, so the
+    /// bits may sum up to at most 53. Larger snowflakes silently lose precision when
+    /// parsed by a JavaScript frontend without `BigInt`. Adding more fields to the
+    /// config afterwards can push it past the limit, see [SnowflakeConfig::is_js_safe].
+    pub fn js_safe(
+        timestamp_bits: u64,
+        machine_id_bits: u64,
+        sequence_bits: u64,
+    ) -> Result<Self, SnowflakeGeneratorError> {
+        let config = Self::new(timestamp_bits, machine_id_bits, sequence_bits)?;
+        if config.is_js_safe() {
+            Ok(config)
+        } else {
+            Err(SnowflakeGeneratorError::InvalidBitConfig)
+        }
+    }
+
+    /// Check whether every snowflake of this config is a safe JavaScript integer.
+    pub fn is_js_safe(&self) -> bool {
+        self.total_bits() <= JS_SAFE_INTEGER_BITS
+    }
+
+    /// Create a new [SnowflakeConfig] whose snowflakes are never negative as an `i64`.
+    ///
+    /// Signed 64-bit columns, like a Postgres `BIGINT` or a JVM `long`, read a
+    /// snowflake with the top bit set as negative, so the bits may sum up to at most
+    /// 63. The default Twitter layout already leaves the top bit unused. Adding more
+    /// fields to the config afterwards can push it past the limit, see
+    /// [SnowflakeConfig::is_i64_safe].
+    pub fn i64_safe(
+        timestamp_bits: u64,
+        machine_id_bits: u64,
+        sequence_bits: u64,
+    ) -> Result<Self, SnowflakeGeneratorError> {
+        let config = Self::new(timestamp_bits, machine_id_bits, sequence_bits)?;
+        if config.is_i64_safe() {
+            Ok(config)
+        } else {
+            Err(SnowflakeGeneratorError::InvalidBitConfig)
+        }
+    }
+
+    /// Check whether every snowflake of this config leaves the top bit unset.
+    pub fn is_i64_safe(&self) -> bool {
+        self.total_bits() <= I64_SAFE_BITS
+    }
+
+    /// The largest sequence ID, one less than the snowflakes per millisecond a
+    /// generator can produce.
+    pub const fn sequence_max(&self) -> u64 {
+        self.sequence_max
+    }
+
+    /// The limit on epoch-relative timestamps, which must stay below it.
+    ///
+    /// Adding this to the epoch gives the point in time at which generation starts
+    /// failing with [SnowflakeGeneratorError::TimestampOverflow]. If the config has an
+    /// era, it is included.
+    pub const fn timestamp_max(&self) -> u64 {
+        self.timestamp_max
+    }
+
+    /// The largest machine ID that fits in the machine ID bits.
+    pub const fn machine_id_max(&self) -> u64 {
+        self.machine_id_mask
+    }
+
+    /// Set how machine IDs that don't fit in the machine ID bits are handled.
+    pub fn with_machine_id_policy(mut self, machine_id_policy: MachineIdPolicy) -> Self {
+        self.machine_id_policy = machine_id_policy;
+        self
+    }
+
+    /// Set what [SnowflakeGenerator::generate] does when the sequence overflows.
+    pub fn with_overflow_policy(mut self, overflow_policy: OverflowPolicy) -> Self {
+        self.overflow_policy = overflow_policy;
+        self
+    }
+
+    /// Set what a generator does when the clock goes backwards.
+    pub fn with_clock_backwards_policy(mut self, policy: ClockBackwardsPolicy) -> Self {
+        self.clock_backwards_policy = policy;
+        self
+    }
+
+    /// Set the memory ordering of the atomic the sequence is kept in.
+    pub fn with_atomic_ordering(mut self, atomic_ordering: AtomicOrdering) -> Self {
+        self.atomic_ordering = atomic_ordering;
+        self
+    }
+
+    /// Set the unit of the timestamp field.
+    ///
+    /// The timestamp function, the epoch, and the minimum timestamp must all be in
+    /// this unit. Generation packs timestamps as they are, the unit is only used to
+    /// turn decoded timestamps back into real time. Returns
+    /// [SnowflakeGeneratorError::InvalidBitConfig] for a custom tick of 0.
+    pub fn with_time_unit(mut self, time_unit: TimeUnit) -> Result<Self, SnowflakeGeneratorError> {
+        if time_unit.nanos_per_tick() == 0 {
+            return Err(SnowflakeGeneratorError::InvalidBitConfig);
+        }
+
+        self.time_unit = time_unit;
+        Ok(self)
+    }
+
+    /// The unit of the timestamp field.
+    pub fn time_unit(&self) -> TimeUnit {
+        self.time_unit
+    }
+
+    /// Set which of the machine ID and the sequence takes up the lowest bits.
+    ///
+    /// This only moves the two fields relative to each other, the widths stay the
+    /// same and are validated the same way in either order. The timestamp stays on
+    /// top, for any other order see [SnowflakeConfig::with_field_order].
+    pub fn with_machine_sequence_order(self, order: MachineSequenceOrder) -> Self {
+        self.with_field_order(match order {
+            MachineSequenceOrder::SequenceBelowMachine => FieldOrder::TimestampMachineSequence,
+            MachineSequenceOrder::MachineBelowSequence => FieldOrder::TimestampSequenceMachine,
+        })
+    }
+
+    /// Set the order of the timestamp, machine ID, and sequence fields.
+    ///
+    /// Only the positions of the three fields change, their widths are validated the
+    /// same way in any order, and they can never overlap. The era and the flavor
+    /// move along with the timestamp, directly above and below it. Everything else
+    /// keeps its place, with the tombstone and prefix above the three fields, and
+    /// the nonce and HMAC tag below them.
+    pub fn with_field_order(mut self, field_order: FieldOrder) -> Self {
+        self.field_order = field_order;
+        self
+    }
+
+    /// The order of the timestamp, machine ID, and sequence fields.
+    pub fn field_order(&self) -> FieldOrder {
+        self.field_order
+    }
+
+    /// Reject timestamps below the given epoch-relative floor.
+    ///
+    /// Generation fails with [SnowflakeGeneratorError::TimestampBelowFloor] instead
+    /// of producing snowflakes that decode to before the floor, e.g. before the
+    /// service launched. This guards against a badly misconfigured clock.
+    pub fn with_min_timestamp_ms(mut self, min_timestamp_ms: u64) -> Self {
+        self.min_timestamp_ms = min_timestamp_ms;
+        self
+    }
+
+    /// Carve an era field out of the machine ID bits.
+    ///
+    /// The era sits directly above the timestamp, and counts how many times the
+    /// timestamp field has wrapped. This multiplies the lifespan of the config by
+    /// `2^era_bits`, at the cost of the same number of machine ID bits. Because
+    /// the era is the high part of an extended timestamp, snowflakes stay monotonic
+    /// across a rollover. The era is derived from the timestamp on every generation,
+    /// so besides the epoch nothing has to be persisted to bump it.
+    ///
+    /// At least one machine ID bit must be left over. Passing 0 removes the era. The
+    /// era can't be changed once the machine ID is split with
+    /// [SnowflakeConfig::with_datacenter_bits].
+    pub fn with_era_bits(mut self, era_bits: u64) -> Result<Self, SnowflakeGeneratorError> {
+        let total_machine_id_bits = self.machine_id_bits + self.era_bits;
+        if era_bits + OVERFLOW_GUARD_BITS > total_machine_id_bits || self.datacenter_bits > 0 {
+            return Err(SnowflakeGeneratorError::InvalidBitConfig);
+        }
+
+        self.era_bits = era_bits;
+        self.machine_id_bits = total_machine_id_bits - era_bits;
+        self.machine_id_mask = build_mask(self.machine_id_bits);
+        self.timestamp_mask = build_mask(self.timestamp_bits + era_bits);
+        self.timestamp_max = calc_max(self.timestamp_bits + era_bits);
+        Ok(self)
+    }
+
+    /// Split the machine ID into a datacenter ID in its high bits and a worker ID in
+    /// its low bits.
+    ///
+    /// This is the original Twitter layout, with 5 bits for each. The two must add
+    /// up to the machine ID bits, which don't include the era, so an era has to be
+    /// set first. Passing 0 datacenter bits removes the split. Generators are
+    /// created from both IDs with [SnowflakeGenerator::new_with_datacenter], and they
+    /// are decoded with [SnowflakeConfig::datacenter_id_of] and
+    /// [SnowflakeConfig::worker_id_of].
+    pub fn with_datacenter_bits(
+        mut self,
+        datacenter_bits: u64,
+        worker_bits: u64,
+    ) -> Result<Self, SnowflakeGeneratorError> {
+        if datacenter_bits + worker_bits != self.machine_id_bits {
+            return Err(SnowflakeGeneratorError::InvalidBitConfig);
+        }
+
+        self.datacenter_bits = datacenter_bits;
+        Ok(self)
+    }
+
+    /// Combine a datacenter and a worker ID into a machine ID.
+    ///
+    /// With [MachineIdPolicy::Reject], returns [SnowflakeGeneratorError::MachineIdOverflow]
+    /// if either doesn't fit in its bits. With [MachineIdPolicy::Mask], each is masked
+    /// separately, so an oversized worker ID never spills into the datacenter ID.
+    pub fn machine_id_from_parts(
+        &self,
+        datacenter_id: u32,
+        worker_id: u32,
+    ) -> Result<u32, SnowflakeGeneratorError> {
+        let worker_bits = self.worker_bits();
+        let datacenter_mask = build_mask(self.datacenter_bits);
+        let worker_mask = build_mask(worker_bits);
+        if self.machine_id_policy == MachineIdPolicy::Reject
+            && (datacenter_id as u64 > datacenter_mask || worker_id as u64 > worker_mask)
+        {
+            return Err(SnowflakeGeneratorError::MachineIdOverflow);
+        }
+
+        let datacenter_id = datacenter_id as u64 & datacenter_mask;
+        let worker_id = worker_id as u64 & worker_mask;
+        Ok((datacenter_id << worker_bits | worker_id) as u32)
+    }
+
+    /// Extract the datacenter ID from a snowflake.
+    ///
+    /// This is 0 unless the machine ID is split with
+    /// [SnowflakeConfig::with_datacenter_bits].
+    pub fn datacenter_id_of(&self, snowflake: u64) -> u64 {
+        self.machine_id_of(snowflake) >> self.worker_bits()
+    }
+
+    /// Extract the worker ID from a snowflake.
+    ///
+    /// This is the whole machine ID unless it is split with
+    /// [SnowflakeConfig::with_datacenter_bits].
+    pub fn worker_id_of(&self, snowflake: u64) -> u64 {
+        self.machine_id_of(snowflake) & build_mask(self.worker_bits())
+    }
+
+    fn worker_bits(&self) -> u64 {
+        self.machine_id_bits - self.datacenter_bits
+    }
+
+    /// Split one machine ID between `instances` generators, e.g. one per process.
+    ///
+    /// Returns the machine ID and config for the generator with index `instance`.
+    /// The instance index takes over the top `ceil(log2(instances))` bits of the
+    /// sequence, which the returned config counts as the low bits of a wider machine
+    /// ID instead, so the instances can never produce the same snowflake. Their
+    /// snowflakes are bit for bit what this config would produce: decoded with it,
+    /// they all carry `machine_id`, and the instance index is the top of the
+    /// sequence. Each instance gets a fraction of the sequence IDs per millisecond.
+    ///
+    /// This needs the machine ID to sit right above the sequence, as in the default
+    /// [FieldOrder], and must leave at least one sequence bit, otherwise
+    /// [SnowflakeGeneratorError::InvalidBitConfig] is returned.
+    /// [SnowflakeGeneratorError::MachineIdOverflow] is returned if `machine_id`
+    /// doesn't fit in the config or `instance` isn't below `instances`.
+    ///
+    /// ```rust
+    /// use frostbit::{SnowflakeConfig, SnowflakeGenerator};
+    ///
+    /// let config = SnowflakeConfig::default();
+    /// let (machine_id, instance_config) = config.split_machine_space(0x10, 4, 3).unwrap();
+    /// let gen = SnowflakeGenerator::new_with_config(machine_id, 0, || Ok(0x1234), instance_config)
+    ///     .unwrap();
+    /// let snowflake = gen.generate().unwrap();
+    /// assert_eq!(config.decode(snowflake).machine_id, 0x10);
+    /// assert_eq!(config.decode(snowflake).sequence, 3 << 10);
+    /// ```
+    pub fn split_machine_space(
+        &self,
+        machine_id: u32,
+        instances: u32,
+        instance: u32,
+    ) -> Result<(u32, SnowflakeConfig), SnowflakeGeneratorError> {
+        let instance_bits = instances.max(1).next_power_of_two().trailing_zeros() as u64;
+        let machine_above_sequence = self
+            .field_order
+            .fields()
+            .windows(2)
+            .any(|pair| pair == [CoreField::MachineId, CoreField::Sequence]);
+        if !machine_above_sequence || self.machine_id_bits + instance_bits > u32::BITS as u64 {
+            return Err(SnowflakeGeneratorError::InvalidBitConfig);
+        }
+        let mut config = self.with_sequence_split(instance_bits)?;
+        if machine_id as u64 > self.machine_id_mask || instance >= instances {
+            return Err(SnowflakeGeneratorError::MachineIdOverflow);
+        }
+
+        config.machine_id_bits += instance_bits;
+        config.machine_id_mask = build_mask(config.machine_id_bits);
+        Ok((machine_id << instance_bits | instance, config))
+    }
+
+    /// The config with `bits` of the sequence given over to another field.
+    ///
+    /// The sequence shrinks by `bits`, which must leave at least one bit of it, or
+    /// this fails with [SnowflakeGeneratorError::InvalidBitConfig]. Nothing else
+    /// moves, so the caller decides where in the old sequence bits the other field
+    /// goes.
+    pub(crate) fn with_sequence_split(&self, bits: u64) -> Result<Self, SnowflakeGeneratorError> {
+        if bits >= self.sequence_bits {
+            return Err(SnowflakeGeneratorError::InvalidBitConfig);
+        }
+
+        let mut config = *self;
+        config.sequence_bits -= bits;
+        config.sequence_mask = build_mask(config.sequence_bits);
+        config.sequence_max = calc_max(config.sequence_bits);
+        Ok(config)
+    }
+
+    /// Add a flavor field between the timestamp and the machine ID.
+    ///
+    /// The flavor is chosen for each snowflake with
+    /// [SnowflakeGenerator::generate_with_flavor], for example to tag which arm of an
+    /// experiment an ID was generated for. It can be at most 8 bits wide, and counts
+    /// towards the 64 bits available. Passing 0 removes the flavor.
+    pub fn with_flavor_bits(mut self, flavor_bits: u64) -> Result<Self, SnowflakeGeneratorError> {
+        self.flavor_bits = flavor_bits;
+        if flavor_bits > u8::BITS as u64 || self.exceeds_width() {
+            Err(SnowflakeGeneratorError::InvalidBitConfig)
+        } else {
+            Ok(self)
+        }
+    }
+
+    /// Reserve the bit above the timestamp as a tombstone flag.
+    ///
+    /// Generated snowflakes never have the bit set, it is only flipped by
+    /// [mark_deleted] to turn a snowflake into a deletion marker. The bit counts
+    /// towards the 64 bits available.
+    pub fn with_tombstone_bit(mut self, enabled: bool) -> Result<Self, SnowflakeGeneratorError> {
+        self.tombstone_bits = enabled as u64;
+        if self.exceeds_width() {
+            Err(SnowflakeGeneratorError::InvalidBitConfig)
+        } else {
+            Ok(self)
+        }
+    }
+
+    /// Mark every snowflake with a constant prefix in its top bits.
+    ///
+    /// The prefix sits above all other fields, and `value` is ORed into every
+    /// snowflake generated with this config, so an entire ID space can be tagged
+    /// with a format marker. Decoding ignores the prefix. The value must fit in
+    /// `bits`, and the bits count towards the 64 bits available. Passing 0 bits
+    /// removes the prefix.
+    pub fn with_prefix(mut self, bits: u64, value: u64) -> Result<Self, SnowflakeGeneratorError> {
+        self.prefix_bits = bits;
+        self.prefix_value = value;
+        if self.exceeds_width() || value > build_mask(bits) {
+            Err(SnowflakeGeneratorError::InvalidBitConfig)
+        } else {
+            Ok(self)
+        }
+    }
+
+    /// Leave the top `reserved_bits` bits of every snowflake unused.
+    ///
+    /// The reserved bits sit above all other fields, prefix included, and are
+    /// always zero, for example to keep snowflakes within 63 bits while holding the
+    /// bit below the sign bit back for a future flag. They count towards the 64 bits
+    /// available, and snowflakes that set them are rejected by
+    /// [SnowflakeConfig::validate]. Passing 0 removes the reservation.
+    pub fn with_reserved_bits(
+        mut self,
+        reserved_bits: u64,
+    ) -> Result<Self, SnowflakeGeneratorError> {
+        self.reserved_bits = reserved_bits;
+        if self.exceeds_width() {
+            Err(SnowflakeGeneratorError::InvalidBitConfig)
+        } else {
+            Ok(self)
+        }
+    }
+
+    /// Reserve the lowest bits of every snowflake for a random nonce.
+    ///
+    /// The nonce sits below all other fields but the HMAC tag, and is filled from an RNG by a
+    /// [NonceSnowflakeGenerator], so that snowflakes can't be enumerated even when
+    /// the timestamp, machine ID, and sequence are predictable. This trades field
+    /// space for unpredictability: the bits count towards the 64 bits available, and
+    /// every nonce bit is one that can't go to the timestamp or sequence. Passing 0
+    /// removes the nonce.
+    pub fn with_nonce_bits(mut self, nonce_bits: u64) -> Result<Self, SnowflakeGeneratorError> {
+        self.nonce_bits = nonce_bits;
+        if self.exceeds_width() {
+            Err(SnowflakeGeneratorError::InvalidBitConfig)
+        } else {
+            Ok(self)
+        }
+    }
+
+    /// Extract the nonce from a snowflake, or 0 if the config has no nonce.
+    pub fn nonce_of(&self, snowflake: u64) -> u64 {
+        (snowflake >> self.nonce_shift()) & build_mask(self.nonce_bits)
+    }
+
+    /// Reserve the lowest bits of every snowflake for a truncated HMAC tag.
+    ///
+    /// The tag is computed over the rest of the snowflake with a secret key by an
+    /// `HmacSnowflakeGenerator`, and checked with `verify_hmac`, both of which need
+    /// the `hmac` feature. The tag sits below all other fields. The bits count
+    /// towards the 64 bits available, and at most 32 can be used. Passing 0 removes
+    /// the tag.
+    pub fn with_hmac_bits(mut self, hmac_bits: u64) -> Result<Self, SnowflakeGeneratorError> {
+        self.hmac_bits = hmac_bits;
+        if hmac_bits > 32 || self.exceeds_width() {
+            Err(SnowflakeGeneratorError::InvalidBitConfig)
+        } else {
+            Ok(self)
+        }
+    }
+
+    /// Extract the flavor from a snowflake, or 0 if the config has no flavor.
+    pub fn flavor_of(&self, snowflake: u64) -> u8 {
+        ((snowflake >> self.flavor_shift()) & build_mask(self.flavor_bits)) as u8
+    }
+
+    /// Decode a snowflake into its timestamp, machine ID, and sequence.
+    ///
+    /// The timestamp is relative to the epoch the snowflake was generated with, and
+    /// includes the era if the config has one. Any other fields, such as the flavor
+    /// or nonce, are ignored.
+    pub fn decode(&self, snowflake: u64) -> DecodedSnowflake {
+        DecodedSnowflake {
+            timestamp: self.timestamp_of(snowflake),
+            machine_id: self.machine_id_of(snowflake),
+            sequence: self.sequence_of(snowflake),
+        }
+    }
+
+    /// Encode a snowflake from its timestamp, machine ID, and sequence.
+    ///
+    /// This is the inverse of [SnowflakeConfig::decode], with the prefix set and every
+    /// other field left unset. Unlike the generators, nothing is read from a clock, so
+    /// this is handy in tests and for importing IDs from other systems. Returns
+    /// [SnowflakeGeneratorError::TimestampOverflow],
+    /// [SnowflakeGeneratorError::MachineIdOverflow], or
+    /// [SnowflakeGeneratorError::SequenceOverflow] if the matching part doesn't fit in
+    /// its bits.
+    ///
+    /// ```rust
+    /// use frostbit::SnowflakeConfig;
+    ///
+    /// let config = SnowflakeConfig::default();
+    /// let snowflake = config.encode(0x1234, 0x10, 7).unwrap();
+    /// assert_eq!(config.decode(snowflake).machine_id, 0x10);
+    /// ```
+    pub fn encode(
+        &self,
+        timestamp: u64,
+        machine_id: u64,
+        sequence: u64,
+    ) -> Result<u64, SnowflakeGeneratorError> {
+        if timestamp > self.timestamp_mask {
+            Err(SnowflakeGeneratorError::TimestampOverflow)
+        } else if machine_id > self.machine_id_mask {
+            Err(SnowflakeGeneratorError::MachineIdOverflow)
+        } else if sequence > self.sequence_mask {
+            Err(SnowflakeGeneratorError::SequenceOverflow)
+        } else {
+            Ok(self.pack(timestamp, machine_id, sequence))
+        }
+    }
+
+    /// Check whether a snowflake could have been generated with this config.
+    ///
+    /// See [SnowflakeConfig::validate] for what is checked.
+    pub fn is_valid(&self, snowflake: u64) -> bool {
+        self.validate(snowflake).is_ok()
+    }
+
+    /// Check that a snowflake could have been generated with this config.
+    ///
+    /// Meant for rejecting malformed IDs, or IDs from a different config, when they
+    /// come from an untrusted source. Returns
+    /// [SnowflakeGeneratorError::InvalidEncoding] if any bits above the configured
+    /// fields are set, or the prefix doesn't match,
+    /// [SnowflakeGeneratorError::TimestampOverflow] if the timestamp is past
+    /// [SnowflakeConfig::timestamp_max], and
+    /// [SnowflakeGeneratorError::TimestampBelowFloor] if it is below the minimum
+    /// timestamp. This can't tell apart a snowflake from a random number that happens
+    /// to fit.
+    pub fn validate(&self, snowflake: u64) -> Result<(), SnowflakeGeneratorError> {
+        let timestamp = self.timestamp_of(snowflake);
+        if !self.fits(snowflake) || self.prefix_of(snowflake) != self.prefix() {
+            Err(SnowflakeGeneratorError::InvalidEncoding)
+        } else if timestamp >= self.timestamp_max {
+            Err(SnowflakeGeneratorError::TimestampOverflow)
+        } else if timestamp < self.min_timestamp_ms {
+            Err(SnowflakeGeneratorError::TimestampBelowFloor)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Make a timestamp relative to `epoch`, and check that it fits in the config.
+    ///
+    /// This is the check every generator applies to its clock reads, so they all
+    /// reject the same timestamps: [SnowflakeGeneratorError::EpochInFuture] before the
+    /// epoch, [SnowflakeGeneratorError::TimestampBelowFloor] below the minimum, and
+    /// [SnowflakeGeneratorError::TimestampOverflow] from
+    /// [SnowflakeConfig::timestamp_max] on.
+    pub(crate) fn epoch_relative(
+        &self,
+        timestamp: u64,
+        epoch: u64,
+    ) -> Result<u64, SnowflakeGeneratorError> {
+        let timestamp = timestamp
+            .checked_sub(epoch)
+            .ok_or(SnowflakeGeneratorError::EpochInFuture)?;
+        if timestamp < self.min_timestamp_ms {
+            Err(SnowflakeGeneratorError::TimestampBelowFloor)
+        } else if timestamp < self.timestamp_max {
+            Ok(timestamp)
+        } else {
+            Err(SnowflakeGeneratorError::TimestampOverflow)
+        }
+    }
+
+    /// Iterate over every snowflake this config can produce.
+    ///
+    /// Every combination of timestamp, machine ID, and sequence is packed, with the
+    /// prefix set and the flavor and tombstone left unset. The timestamps run from
+    /// the minimum timestamp up to, but excluding, [SnowflakeConfig::timestamp_max],
+    /// which no generator hands out, so every snowflake passes
+    /// [SnowflakeConfig::is_valid]. The iterator is lazy, but there are roughly
+    /// `2^(timestamp_bits + machine_id_bits + sequence_bits)` snowflakes, so this is
+    /// only practical for tiny configs used in exhaustive tests.
+    pub fn all_valid_ids(&self) -> impl Iterator<Item = u64> {
+        let config = *self;
+        (config.min_timestamp_ms..config.timestamp_max).flat_map(move |timestamp| {
+            (0..=config.machine_id_mask).flat_map(move |machine_id| {
+                (0..=config.sequence_mask)
+                    .map(move |sequence| config.pack(timestamp, machine_id, sequence))
+            })
+        })
+    }
+
+    /// Describe where each field of the snowflake lives.
+    ///
+    /// Fields are listed from the most significant to the least significant, and
+    /// only fields that are actually configured are included.
+    #[cfg(feature = "alloc")]
+    pub fn layout(&self) -> Vec<FieldLayout> {
+        let machine_id = if self.datacenter_bits > 0 {
+            [
+                ("datacenter_id", self.datacenter_bits),
+                ("worker_id", self.worker_bits()),
+            ]
+        } else {
+            [("datacenter_id", 0), ("machine_id", self.machine_id_bits)]
+        };
+        let mut fields = vec![
+            ("prefix", self.prefix_bits),
+            ("tombstone", self.tombstone_bits),
+        ];
+        for field in self.field_order.fields() {
+            match field {
+                CoreField::Timestamp => fields.extend([
+                    ("era", self.era_bits),
+                    ("timestamp", self.timestamp_bits),
+                    ("flavor", self.flavor_bits),
+                ]),
+                CoreField::MachineId => fields.extend(machine_id),
+                CoreField::Sequence => fields.push(("sequence", self.sequence_bits)),
+            }
+        }
+        fields.extend([("nonce", self.nonce_bits), ("hmac", self.hmac_bits)]);
+
+        let mut shift = self.total_bits();
+        fields
+            .into_iter()
+            .filter(|(_, width)| *width > 0)
+            .map(|(name, width)| {
+                shift -= width;
+                FieldLayout { name, shift, width }
+            })
+            .collect()
+    }
+
+    /// Extract the epoch-relative timestamp from a snowflake.
+    ///
+    /// If the config has an era, this is the extended timestamp including it.
+    pub(crate) fn timestamp_of(&self, snowflake: u64) -> u64 {
+        (snowflake >> self.timestamp_shift()) & self.timestamp_mask
+    }
+
+    /// Extract the machine ID from a snowflake.
+    pub(crate) fn machine_id_of(&self, snowflake: u64) -> u64 {
+        (snowflake >> self.machine_id_shift()) & self.machine_id_mask
+    }
+
+    /// Extract the sequence ID from a snowflake.
+    pub(crate) fn sequence_of(&self, snowflake: u64) -> u64 {
+        (snowflake >> self.sequence_shift()) & self.sequence_mask
+    }
+
+    /// Check that a snowflake doesn't set any bits above the configured fields.
+    pub(crate) fn fits(&self, snowflake: u64) -> bool {
+        snowflake.checked_shr(self.total_bits() as u32).unwrap_or(0) == 0
+    }
+
+    /// Pack the fields of a snowflake, dropping any bits that don't fit.
+    ///
+    /// The prefix, if any, is always set.
+    pub(crate) fn pack(&self, timestamp: u64, machine_id: u64, sequence: u64) -> u64 {
+        self.prefix()
+            | (timestamp & self.timestamp_mask) << self.timestamp_shift()
+            | (machine_id & self.machine_id_mask) << self.machine_id_shift()
+            | (sequence & self.sequence_mask) << self.sequence_shift()
+    }
+
+    pub(crate) fn timestamp_shift(&self) -> u64 {
+        self.flavor_shift() + self.flavor_bits
+    }
+
+    fn machine_id_shift(&self) -> u64 {
+        self.core_shift(CoreField::MachineId)
+    }
+
+    fn sequence_shift(&self) -> u64 {
+        self.core_shift(CoreField::Sequence)
+    }
+
+    fn flavor_shift(&self) -> u64 {
+        self.core_shift(CoreField::Timestamp)
+    }
+
+    /// The shift of one of the fields ordered by [FieldOrder], past the fields
+    /// ordered below it.
+    fn core_shift(&self, field: CoreField) -> u64 {
+        let below: u64 = self
+            .field_order
+            .fields()
+            .into_iter()
+            .rev()
+            .take_while(|other| *other != field)
+            .map(|other| self.core_width(other))
+            .sum();
+        self.low_bits() + below
+    }
+
+    /// The width of one of the fields ordered by [FieldOrder], including the era
+    /// and flavor that move along with the timestamp.
+    fn core_width(&self, field: CoreField) -> u64 {
+        match field {
+            CoreField::Timestamp => self.era_bits + self.timestamp_bits + self.flavor_bits,
+            CoreField::MachineId => self.machine_id_bits,
+            CoreField::Sequence => self.sequence_bits,
+        }
+    }
+
+    /// The number of bits below the machine ID and sequence, taken up by the
+    /// nonce and the HMAC tag.
+    fn low_bits(&self) -> u64 {
+        self.nonce_bits + self.hmac_bits
+    }
+
+    pub(crate) fn nonce_shift(&self) -> u64 {
+        self.hmac_bits
+    }
+
+    fn tombstone_shift(&self) -> u64 {
+        self.low_bits()
+            + self.core_width(CoreField::Timestamp)
+            + self.core_width(CoreField::MachineId)
+            + self.core_width(CoreField::Sequence)
+    }
+
+    fn prefix(&self) -> u64 {
+        if self.prefix_bits > 0 {
+            self.prefix_value << (self.tombstone_shift() + self.tombstone_bits)
+        } else {
+            0
+        }
+    }
+
+    fn prefix_of(&self, snowflake: u64) -> u64 {
+        if self.prefix_bits > 0 {
+            snowflake
+                & (build_mask(self.prefix_bits) << (self.tombstone_shift() + self.tombstone_bits))
+        } else {
+            0
+        }
+    }
+
+    /// Whether the fields and the reserved bits together need more than 64 bits.
+    fn exceeds_width(&self) -> bool {
+        self.total_bits() + self.reserved_bits > 64
+    }
+
+    /// The number of bits taken up by fields, not counting the reserved bits.
+    fn total_bits(&self) -> u64 {
+        self.prefix_bits
+            + self.tombstone_bits
+            + self.era_bits
+            + self.timestamp_bits
+            + self.flavor_bits
+            + self.machine_id_bits
+            + self.sequence_bits
+            + self.nonce_bits
+            + self.hmac_bits
+    }
+
+    fn validate_config(
+        machine_id_bits: u64,
+        sequence_bits: u64,
+        timestamp_bits: u64,
+    ) -> Result<(), SnowflakeGeneratorError> {
+        let bit_sum = timestamp_bits + machine_id_bits + sequence_bits;
+        if bit_sum > 64 {
+            return Err(SnowflakeGeneratorError::InvalidBitConfig);
+        }
+
+        if machine_id_bits == 0 || sequence_bits == 0 || timestamp_bits == 0 {
+            Err(SnowflakeGeneratorError::InvalidBitConfig)
+        } else {
+            Ok(())
+        }
+    }
+}
+
+impl Default for SnowflakeConfig {
+    fn default() -> Self {
+        Self::new(
+            DEFAULT_TIMESTAMP_BITS,
+            DEFAULT_MACHINE_ID_BITS,
+            DEFAULT_SEQUENCE_ID_BITS,
+        )
+        .expect("Default values incorrect")
+    }
+}
+
+/// How a generator handles a machine ID that doesn't fit in the machine ID bits.
+///
+/// Wrapping the machine ID around the size of the field is the same as masking
+/// it, so both are covered by [MachineIdPolicy::Mask].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum MachineIdPolicy {
+    /// Fail construction with [SnowflakeGeneratorError::MachineIdOverflow].
+    #[default]
+    Reject,
+    /// Silently drop the high bits of the machine ID. Different machine IDs
+    /// can end up producing colliding snowflakes.
+    Mask,
+}
+
+impl MachineIdPolicy {
+    fn check(
+        &self,
+        machine_id: u32,
+        config: &SnowflakeConfig,
+    ) -> Result<(), SnowflakeGeneratorError> {
+        match self {
+            Self::Reject if machine_id as u64 > config.machine_id_mask => {
+                Err(SnowflakeGeneratorError::MachineIdOverflow)
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+/// What a generator does when the sequence overflows within a millisecond.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum OverflowPolicy {
+    /// Fail with [SnowflakeGeneratorError::SequenceOverflow].
+    #[default]
+    Error,
+    /// Busy-wait, re-reading the timestamp function until it moves on to the next
+    /// millisecond. This burns a core for up to a millisecond, and hangs for as long
+    /// as the clock stands still. Errors from reading the timestamp, including
+    /// [SnowflakeGeneratorError::TimestampOverflow], are still returned.
+    SpinWait,
+}
+
+/// What a generator does when the clock reads earlier than the last timestamp used.
+///
+/// This usually happens when NTP steps the clock back. It also happens right after
+/// [SnowflakeGenerator::prewarm] or [SnowflakeGenerator::generate_batch] dated
+/// snowflakes ahead of the clock, which counts the same way.
+///
+/// The generator always remembers the latest timestamp it has used, and never
+/// moves back from it. The choice is between carrying on from there, which keeps
+/// snowflakes strictly increasing across clock adjustments but dates them later
+/// than they were really created until the clock catches up, and failing loudly so
+/// that the drift is noticed, at the cost of not generating anything meanwhile.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ClockBackwardsPolicy {
+    /// Keep generating with the last timestamp used until the clock catches up.
+    /// Snowflakes stay unique and increasing, but are dated later than the clock.
+    /// They share the sequence IDs of that one timestamp, so a long step back runs
+    /// into [SnowflakeGeneratorError::SequenceOverflow] (or waits, under
+    /// [OverflowPolicy::SpinWait]) once they are used up.
+    #[default]
+    Lenient,
+    /// Fail with [SnowflakeGeneratorError::ClockWentBackwards] if the clock is more
+    /// than `tolerance_ms` behind the last timestamp used. Smaller steps back are
+    /// handled as with [ClockBackwardsPolicy::Lenient].
+    Reject { tolerance_ms: u64 },
+}
+
+/// The memory ordering of the atomic updates a generator makes to its sequence.
+///
+/// Every snowflake is allocated with a read-modify-write on a single atomic, and the
+/// read-modify-writes of one atomic are totally ordered under any ordering. So
+/// snowflakes are unique and strictly increasing within a generator either way,
+/// single-threaded or not, and the choice only matters for other memory.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum AtomicOrdering {
+    /// Sequentially consistent, the default. Generating a snowflake synchronizes
+    /// with the threads that generated earlier ones, so whatever a thread wrote
+    /// before generating a snowflake is visible to a thread that later generates a
+    /// larger one.
+    #[default]
+    SeqCst,
+    /// Relaxed, which is cheaper on weakly ordered CPUs like ARM. Snowflakes carry
+    /// no happens-before relation between threads, so their order must not be used
+    /// to reason about other memory. That is fine when generating from one thread,
+    /// or when the snowflakes are only used as IDs.
+    Relaxed,
+}
+
+impl AtomicOrdering {
+    pub(crate) fn ordering(&self) -> core::sync::atomic::Ordering {
+        match self {
+            Self::SeqCst => core::sync::atomic::Ordering::SeqCst,
+            Self::Relaxed => core::sync::atomic::Ordering::Relaxed,
+        }
+    }
+}
+
+/// The length of a tick of the timestamp field.
+///
+/// Despite the names used throughout the crate, the timestamp doesn't have to count
+/// milliseconds. Finer ticks give more snowflakes per second, at the cost of the
+/// timestamp field overflowing sooner.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum TimeUnit {
+    /// Milliseconds, the default.
+    #[default]
+    Millis,
+    /// Microseconds.
+    Micros,
+    /// A custom tick of the given number of nanoseconds, e.g. `Custom(10_000_000)`
+    /// for the 10 millisecond ticks of Sonyflake.
+    Custom(u64),
+}
+
+impl TimeUnit {
+    /// Convert a number of ticks into a duration.
+    pub fn to_duration(&self, ticks: u64) -> core::time::Duration {
+        let nanos = ticks as u128 * self.nanos_per_tick() as u128;
+        core::time::Duration::new(
+            (nanos / NANOS_PER_SECOND) as u64,
+            (nanos % NANOS_PER_SECOND) as u32,
+        )
+    }
+
+    /// Convert a duration into a number of whole ticks, rounding down.
+    pub fn from_duration(&self, duration: core::time::Duration) -> u64 {
+        (duration.as_nanos() / self.nanos_per_tick() as u128) as u64
+    }
+
+    fn nanos_per_tick(&self) -> u64 {
+        match self {
+            Self::Millis => 1_000_000,
+            Self::Micros => 1_000,
+            Self::Custom(nanos) => *nanos,
+        }
+    }
+}
+
+/// The relative order of the machine ID and sequence fields.
+///
+/// Twitter-style snowflakes put the sequence in the lowest bits, some legacy
+/// formats put the machine ID there instead. Either way the timestamp and
+/// everything above it stays where it is. This is a shorthand for the two
+/// [FieldOrder]s with the timestamp on top.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum MachineSequenceOrder {
+    /// `[.. | machine_id | sequence]`, the default.
+    #[default]
+    SequenceBelowMachine,
+    /// `[.. | sequence | machine_id]`.
+    MachineBelowSequence,
+}
+
+/// The order of the timestamp, machine ID, and sequence fields, from the most
+/// significant to the least significant.
+///
+/// Snowflakes only sort by creation time when the timestamp is on top. With any
+/// other order, use [cmp_by_timestamp] to sort them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum FieldOrder {
+    /// `[timestamp | machine_id | sequence]`, the Twitter layout and the default.
+    #[default]
+    TimestampMachineSequence,
+    /// `[timestamp | sequence | machine_id]`, as used by Sonyflake.
+    TimestampSequenceMachine,
+    /// `[machine_id | timestamp | sequence]`.
+    MachineTimestampSequence,
+    /// `[machine_id | sequence | timestamp]`.
+    MachineSequenceTimestamp,
+    /// `[sequence | timestamp | machine_id]`.
+    SequenceTimestampMachine,
+    /// `[sequence | machine_id | timestamp]`.
+    SequenceMachineTimestamp,
+}
+
+impl FieldOrder {
+    fn fields(&self) -> [CoreField; 3] {
+        use CoreField::*;
+
+        match self {
+            Self::TimestampMachineSequence => [Timestamp, MachineId, Sequence],
+            Self::TimestampSequenceMachine => [Timestamp, Sequence, MachineId],
+            Self::MachineTimestampSequence => [MachineId, Timestamp, Sequence],
+            Self::MachineSequenceTimestamp => [MachineId, Sequence, Timestamp],
+            Self::SequenceTimestampMachine => [Sequence, Timestamp, MachineId],
+            Self::SequenceMachineTimestamp => [Sequence, MachineId, Timestamp],
+        }
+    }
+}
+
+/// The fields whose order is set by [FieldOrder].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CoreField {
+    Timestamp,
+    MachineId,
+    Sequence,
+}
+
+/// The position of a single field within a snowflake.
+///
+/// Returned by [SnowflakeConfig::layout]. The shift is the bit offset of the
+/// field's least significant bit, and the width is the number of bits it occupies.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct FieldLayout {
+    pub name: &'static str,
+    pub shift: u64,
+    pub width: u64,
+}
+
+/// The fields of a snowflake, as returned by [SnowflakeConfig::decode].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct DecodedSnowflake {
+    pub timestamp: u64,
+    pub machine_id: u64,
+    pub sequence: u64,
+}
+
+/// A new snowflake and the fields it was packed from, as returned by
+/// [SnowflakeGenerator::generate_detailed].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct GeneratedSnowflake {
+    pub id: u64,
+    pub timestamp: u64,
+    pub machine_id: u64,
+    pub sequence: u64,
+}
+
+pub(crate) const fn build_mask(bits: u64) -> u64 {
+    if bits >= 64 {
+        u64::MAX
+    } else {
+        (1 << bits) - 1
+    }
+}
+
+pub(crate) const fn calc_max(bits: u64) -> u64 {
+    2u64.pow(bits as u32) - 1
+}
+
+/// The splitmix64 finalizer, a cheap mix where every input bit affects every
+/// output bit.
+pub(crate) const fn splitmix64(value: u64) -> u64 {
+    let mut z = value;
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    };
+
+    use rstest::rstest;
+
+    use super::*;
+
+    #[test]
+    fn test_timestamp_generation() {
+        const TIMESTAMP: u64 = 0x1234u64;
+        let timestamp_fn = || Ok(TIMESTAMP);
+        let machine_id = 0x10u32;
+        let epoch = 0u64;
+
+        let generator = SnowflakeGenerator::new(machine_id, epoch, timestamp_fn).unwrap();
+        let snowflake = generator.generate().unwrap();
+        assert_eq!(snowflake, 0x48D010000);
+
+        let snowflake = generator.generate().unwrap();
+        assert_eq!(snowflake, 0x48D010001);
+    }
+
+    #[test]
+    fn test_error_display() {
+        assert_eq!(
+            SnowflakeGeneratorError::from("clock unavailable").to_string(),
+            "failed to get timestamp: clock unavailable"
+        );
+        assert_eq!(
+            SnowflakeGeneratorError::SequenceOverflow.to_string(),
+            "sequence overflowed for the current millisecond"
+        );
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_error_boxes() {
+        fn generate() -> Result<u64, Box<dyn std::error::Error>> {
+            let generator = SnowflakeGenerator::new(0x400, 0, || Ok(0x1234))?;
+            Ok(generator.generate()?)
+        }
+
+        let error = generate().unwrap_err();
+        assert!(error.downcast_ref::<SnowflakeGeneratorError>().is_some());
+    }
+
+    #[test]
+    fn test_sequence_overflow() {
+        const TIMESTAMP: u64 = 0x1234u64;
+        let timestamp_fn = || Ok(TIMESTAMP);
+        let machine_id = 0x10u32;
+        let epoch = 0u64;
+        let sequence_id_max = SnowflakeConfig::default().sequence_max + 1;
+
+        let generator = SnowflakeGenerator::new(machine_id, epoch, timestamp_fn).unwrap();
+        // iterate over generation until right before sequence overflow
+        for _ in 0..sequence_id_max {
+            generator.generate().unwrap();
+        }
+
+        let res = generator.generate();
+        assert!(matches!(
+            res,
+            Err(SnowflakeGeneratorError::SequenceOverflow)
+        ));
+    }
+
+    #[test]
+    fn test_timestamp_overflow() {
+        let timestamp: u64 = SnowflakeConfig::default().timestamp_max + 1;
+        let call_count = Arc::new(AtomicU64::new(0));
+        let timestamp_fn = || {
+            let count = call_count.fetch_add(1, Ordering::SeqCst);
+            if count < 1 {
+                Ok(0)
+            } else {
+                Ok(timestamp)
+            }
+        };
+        let machine_id = 0x10u32;
+        let epoch = 0u64;
+
+        let generator = SnowflakeGenerator::new(machine_id, epoch, timestamp_fn).unwrap();
+        let result = generator.generate();
+        assert!(matches!(
+            result,
+            Err(SnowflakeGeneratorError::TimestampOverflow)
+        ));
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn test_generate_by_waits_for_clock() {
+        let sequence_id_max = SnowflakeConfig::default().sequence_max + 1;
+        let call_count = AtomicU64::new(0);
+        let timestamp_fn = || {
+            // construction, a full millisecond of sequence IDs, the overflowing
+            // call, and a few polls before the clock advances
+            let count = call_count.fetch_add(1, Ordering::SeqCst);
+            if count < sequence_id_max + 5 {
+                Ok(0)
+            } else {
+                Ok(1)
+            }
+        };
+
+        let generator = SnowflakeGenerator::new(0x10, 0, timestamp_fn).unwrap();
+        for _ in 0..sequence_id_max {
+            generator.generate().unwrap();
+        }
+
+        let deadline = std::time::Instant::now() + std::time::Duration::from_secs(10);
+        let snowflake = generator.generate_by(deadline).unwrap();
+        assert_eq!(snowflake, 0x410000);
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn test_generate_by_timeout() {
+        let timestamp_fn = || Ok(0x1234);
+        let sequence_id_max = SnowflakeConfig::default().sequence_max + 1;
+
+        let generator = SnowflakeGenerator::new(0x10, 0, timestamp_fn).unwrap();
+        for _ in 0..sequence_id_max {
+            generator.generate().unwrap();
+        }
+
+        let deadline = std::time::Instant::now() + std::time::Duration::from_millis(10);
+        let result = generator.generate_by(deadline);
+        assert!(matches!(result, Err(SnowflakeGeneratorError::WaitTimeout)));
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn test_measure_throughput() {
+        let generator = SnowflakeGenerator::new(0x10, 1_600_000_000_000, unix_millis).unwrap();
+
+        let count = generator.measure_throughput(std::time::Duration::from_millis(20));
+        assert!(count > 0);
+        generator.generate().unwrap();
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn test_measure_throughput_stuck_clock() {
+        let generator = SnowflakeGenerator::new(0x10, 0, || Ok(0x1234)).unwrap();
+        let count = generator.measure_throughput(std::time::Duration::from_millis(5));
+        assert_eq!(count, SnowflakeConfig::default().sequence_max + 1);
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn test_generate_avoiding() {
+        let config = SnowflakeConfig::new(42, 5, 2).unwrap();
+        let generator =
+            SnowflakeGenerator::new_with_config(0x10, 0, || Ok(0x1234), config).unwrap();
+        let existing = [0, 1, 3]
+            .into_iter()
+            .map(|sequence| config.pack(0x1234, 0x10, sequence))
+            .collect();
+
+        let snowflake = generator.generate_avoiding(&existing).unwrap();
+        assert_eq!(snowflake, config.pack(0x1234, 0x10, 2));
+
+        let result = generator.generate_avoiding(&existing);
+        assert!(matches!(
+            result,
+            Err(SnowflakeGeneratorError::SequenceOverflow)
+        ));
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn test_new_with_retries() {
+        let call_count = AtomicU64::new(0);
+        let timestamp_fn = || {
+            let count = call_count.fetch_add(1, Ordering::SeqCst);
+            if count < 2 {
+                Err("Clock warming up")
+            } else {
+                Ok(0x1234)
+            }
+        };
+
+        let config = SnowflakeConfig::default();
+        let backoff = std::time::Duration::from_millis(1);
+        let generator =
+            SnowflakeGenerator::new_with_retries(0x10, 0, timestamp_fn, config, 2, backoff)
+                .unwrap();
+        assert_eq!(generator.generate().unwrap(), 0x48D010000);
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn test_new_with_retries_source_error() {
+        let call_count = AtomicU64::new(0);
+        let clock = FallibleTimestamp(|| {
+            if call_count.fetch_add(1, Ordering::SeqCst) == 0 {
+                // the UNIX epoch is before now, so this fails with a SystemTimeError
+                std::time::UNIX_EPOCH
+                    .duration_since(std::time::SystemTime::now())
+                    .map(|_| 0)
+            } else {
+                Ok(0x1234)
+            }
+        });
+
+        let config = SnowflakeConfig::default();
+        let backoff = std::time::Duration::from_millis(1);
+        let generator =
+            SnowflakeGenerator::new_with_retries(0x10, 0, clock, config, 1, backoff).unwrap();
+        assert_eq!(generator.generate().unwrap(), 0x48D010000);
+        assert_eq!(call_count.load(Ordering::SeqCst), 3);
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn test_new_with_retries_exhausted() {
+        let call_count = AtomicU64::new(0);
+        let timestamp_fn = || {
+            call_count.fetch_add(1, Ordering::SeqCst);
+            Err("Timestamp error")
+        };
+
+        let config = SnowflakeConfig::default();
+        let backoff = std::time::Duration::from_millis(1);
+        let generator =
+            SnowflakeGenerator::new_with_retries(0x10, 0, timestamp_fn, config, 3, backoff);
+        assert!(matches!(
+            generator,
+            Err(SnowflakeGeneratorError::TimestampError("Timestamp error"))
+        ));
+        assert_eq!(call_count.load(Ordering::SeqCst), 4);
+    }
+
+    struct CountingAllocator {
+        count: AtomicU64,
+    }
+
+    impl SequenceAllocator for CountingAllocator {
+        fn allocate(&self, timestamp: u64) -> Result<TimestampSequence, SnowflakeGeneratorError> {
+            let sequence = self.count.fetch_add(2, Ordering::SeqCst);
+            Ok(TimestampSequence {
+                sequence,
+                timestamp,
+            })
+        }
+    }
+
+    #[test]
+    fn test_custom_sequence_allocator() {
+        let allocator = CountingAllocator {
+            count: AtomicU64::new(4),
+        };
+        let config = SnowflakeConfig::default();
+        let generator =
+            SnowflakeGenerator::new_with_allocator(0x10, 0, || Ok(0x1234), config, allocator)
+                .unwrap();
+
+        assert_eq!(generator.generate().unwrap(), 0x48D010004);
+        assert_eq!(generator.generate().unwrap(), 0x48D010006);
+    }
+
+    #[test]
+    fn test_default_sequence_allocator() {
+        let allocator = TimestampSequenceGenerator::new(0x1234, SnowflakeConfig::default());
+        let timestamp_sequence = allocator.allocate(0x1235).unwrap();
+        assert_eq!(
+            timestamp_sequence,
+            TimestampSequence {
+                sequence: 0,
+                timestamp: 0x1235
+            }
+        );
+    }
+
+    #[test]
+    fn test_prewarm() {
+        let generator = SnowflakeGenerator::new(0x10, 0x1000, || Ok(0x1234)).unwrap();
+        generator.prewarm(0x2234).unwrap();
+
+        assert_eq!(generator.generate().unwrap(), 0x48D010000);
+        assert_eq!(generator.generate().unwrap(), 0x48D010001);
+
+        // prewarming backwards leaves the generator where it is
+        generator.prewarm(0x1234).unwrap();
+        assert_eq!(generator.generate().unwrap(), 0x48D010002);
+    }
+
+    #[test]
+    fn test_prewarm_overflow() {
+        let generator = SnowflakeGenerator::new(0x10, 0, || Ok(0x1234)).unwrap();
+        let timestamp_max = SnowflakeConfig::default().timestamp_max;
+        let result = generator.prewarm(timestamp_max);
+        assert!(matches!(
+            result,
+            Err(SnowflakeGeneratorError::TimestampOverflow)
+        ));
+    }
+
+    #[test]
+    fn test_timestamp_failure() {
+        let timestamp_fn = || Err("Timestamp error");
+        let machine_id = 0x10u32;
+        let epoch = 0u64;
+
+        let generator = SnowflakeGenerator::new(machine_id, epoch, timestamp_fn);
+        assert!(matches!(
+            generator,
+            Err(SnowflakeGeneratorError::TimestampError("Timestamp error"))
+        ));
+    }
+
+    #[test]
+    fn test_invalid_config_too_many_bits() {
+        let config = SnowflakeConfig::new(41, 10, 24);
+        assert!(matches!(
+            config,
+            Err(SnowflakeGeneratorError::InvalidBitConfig)
+        ));
+    }
+
+    #[test]
+    fn test_machine_id_boundary() {
+        let machine_id = SnowflakeConfig::default().machine_id_mask as u32;
+        let generator = SnowflakeGenerator::new(machine_id, 0, || Ok(0x1234)).unwrap();
+        let snowflake = generator.generate().unwrap();
+        assert_eq!(snowflake, 0x48D3FF000);
+    }
+
+    #[test]
+    fn test_machine_id_overflow_rejected() {
+        let machine_id = SnowflakeConfig::default().machine_id_mask as u32 + 1;
+        let generator = SnowflakeGenerator::new(machine_id, 0, || Ok(0x1234));
+        assert!(matches!(
+            generator,
+            Err(SnowflakeGeneratorError::MachineIdOverflow)
+        ));
+    }
+
+    #[test]
+    fn test_machine_id_overflow_masked() {
+        let config = SnowflakeConfig::default().with_machine_id_policy(MachineIdPolicy::Mask);
+        let generator =
+            SnowflakeGenerator::new_with_config(0x410, 0, || Ok(0x1234), config).unwrap();
+        let snowflake = generator.generate().unwrap();
+        assert_eq!(snowflake, 0x48D010000);
+    }
+
+    #[test]
+    fn test_could_have_produced() {
+        let clock = AtomicU64::new(0x1234);
+        let generator =
+            SnowflakeGenerator::new(0x10, 0, || Ok(clock.load(Ordering::SeqCst))).unwrap();
+        let config = SnowflakeConfig::default();
+
+        let snowflake = generator.generate().unwrap();
+        assert!(generator.could_have_produced(snowflake));
+        assert!(generator.could_have_produced(config.pack(0x2000, 0x10, 7)));
+
+        // different machine, from before the generator started, reserved bit set
+        assert!(!generator.could_have_produced(config.pack(0x1234, 0x11, 0)));
+        assert!(!generator.could_have_produced(config.pack(0x1233, 0x10, 0)));
+        assert!(!generator.could_have_produced(snowflake | 1 << 63));
+    }
+
+    #[test]
+    fn test_with_clock() {
+        let config = SnowflakeConfig::new(42, 5, 8).unwrap();
+        let generator =
+            SnowflakeGenerator::new_with_config(0x10, 1000, || Ok(0x2000), config).unwrap();
+        generator.generate().unwrap();
+
+        let mirrored = generator.with_clock(|| Ok(1000 + 0x1234)).unwrap();
+        let snowflake = mirrored.generate().unwrap();
+        assert_eq!(snowflake, config.pack(0x1234, 0x10, 0));
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn test_shared_clones() {
+        let config = SnowflakeConfig::new(42, 5, 2).unwrap();
+        let clock = Arc::new(AtomicU64::new(0x1234));
+        let clock_fn = {
+            let clock = clock.clone();
+            move || Ok(clock.load(Ordering::SeqCst))
+        };
+        let generator = SnowflakeGenerator::new_with_config(0x10, 0, clock_fn, config)
+            .unwrap()
+            .into_shared();
+        let clone = generator.clone();
+
+        let mut ids = Vec::new();
+        for _ in 0..2 {
+            ids.push(generator.generate().unwrap());
+            ids.push(clone.generate().unwrap());
+        }
+        let expected = (0..4)
+            .map(|sequence| config.pack(0x1234, 0x10, sequence))
+            .collect::<Vec<_>>();
+        assert_eq!(ids, expected);
+        assert!(matches!(
+            clone.generate(),
+            Err(SnowflakeGeneratorError::SequenceOverflow)
+        ));
+
+        clock.store(0x1235, Ordering::SeqCst);
+        assert_eq!(
+            clone.clone().generate().unwrap(),
+            config.pack(0x1235, 0x10, 0)
+        );
+        assert_eq!(generator.generate().unwrap(), config.pack(0x1235, 0x10, 1));
+    }
+
+    #[test]
+    fn test_rotate_machine_id() {
+        let generator = SnowflakeGenerator::new(0x10, 0, || Ok(0x1234)).unwrap();
+        assert_eq!(generator.generate().unwrap(), 0x48D010000);
+
+        generator.rotate_machine_id(0x11).unwrap();
+        assert_eq!(generator.generate().unwrap(), 0x48D011001);
+        assert!(!generator.could_have_produced(0x48D010000));
+
+        generator.rotate_machine_id(0x10).unwrap();
+        assert_eq!(generator.generate().unwrap(), 0x48D010002);
+
+        let result = generator.rotate_machine_id(0x400);
+        assert!(matches!(
+            result,
+            Err(SnowflakeGeneratorError::MachineIdOverflow)
+        ));
+    }
+
+    #[test]
+    fn test_decode_timestamp_ms() {
+        const EPOCH: u64 = 1_288_834_974_657;
+        let config = SnowflakeConfig::new(42, 5, 8).unwrap();
+        let generator =
+            SnowflakeGenerator::new_with_config(0x10, EPOCH, || Ok(EPOCH + 0x2_1234_5678), config)
+                .unwrap();
+
+        let snowflake = generator.generate().unwrap();
+        assert_eq!(
+            generator.decode_timestamp_ms(snowflake),
+            EPOCH + 0x2_1234_5678
+        );
+    }
+
+    #[test]
+    fn test_overflow_spin_wait() {
+        let config = SnowflakeConfig::new(41, 10, 2)
+            .unwrap()
+            .with_overflow_policy(OverflowPolicy::SpinWait);
+        let reads = AtomicU64::new(0);
+        // the clock advances every 8 reads
+        let timestamp_fn = || Ok(0x1234 + reads.fetch_add(1, Ordering::SeqCst) / 8);
+        let generator = SnowflakeGenerator::new_with_config(0x10, 0, timestamp_fn, config).unwrap();
+
+        for _ in 0..4 {
+            generator.generate().unwrap();
+        }
+        let snowflake = generator.generate().unwrap();
+        assert_eq!(config.timestamp_of(snowflake), 0x1235);
+        assert_eq!(config.sequence_of(snowflake), 0);
+    }
+
+    #[test]
+    fn test_overflow_spin_wait_timestamp_overflow() {
+        let config = SnowflakeConfig::new(8, 10, 2)
+            .unwrap()
+            .with_overflow_policy(OverflowPolicy::SpinWait);
+        let reads = AtomicU64::new(0);
+        let timestamp_fn = || Ok(0xFE + reads.fetch_add(1, Ordering::SeqCst) / 8);
+        let generator = SnowflakeGenerator::new_with_config(0x10, 0, timestamp_fn, config).unwrap();
+
+        for _ in 0..4 {
+            generator.generate().unwrap();
+        }
+        let result = generator.generate();
+        assert!(matches!(
+            result,
+            Err(SnowflakeGeneratorError::TimestampOverflow)
+        ));
+    }
+
+    #[test]
+    fn test_try_generate_and_generate_blocking() {
+        let config = SnowflakeConfig::new(41, 10, 2)
+            .unwrap()
+            .with_overflow_policy(OverflowPolicy::SpinWait);
+        let reads = AtomicU64::new(0);
+        // the clock advances every 8 reads
+        let timestamp_fn = || Ok(0x1234 + reads.fetch_add(1, Ordering::SeqCst) / 8);
+        let generator = SnowflakeGenerator::new_with_config(0x10, 0, timestamp_fn, config).unwrap();
+
+        for _ in 0..4 {
+            generator.try_generate().unwrap();
+        }
+        assert!(matches!(
+            generator.try_generate(),
+            Err(SnowflakeGeneratorError::SequenceOverflow)
+        ));
+
+        let config = SnowflakeConfig::new(41, 10, 2).unwrap();
+        reads.store(0, Ordering::SeqCst);
+        let generator = SnowflakeGenerator::new_with_config(0x10, 0, timestamp_fn, config).unwrap();
+        for _ in 0..4 {
+            generator.generate_blocking().unwrap();
+        }
+        let snowflake = generator.generate_blocking().unwrap();
+        assert_eq!(config.timestamp_of(snowflake), 0x1235);
+        assert_eq!(config.sequence_of(snowflake), 0);
+    }
+
+    #[test]
+    fn test_generate_blocking_timestamp_error() {
+        let config = SnowflakeConfig::new(41, 10, 2).unwrap();
+        let reads = AtomicU64::new(0);
+        let timestamp_fn = || match reads.fetch_add(1, Ordering::SeqCst) {
+            0..=4 => Ok(0x1234),
+            _ => Err("clock unavailable"),
+        };
+        let generator = SnowflakeGenerator::new_with_config(0x10, 0, timestamp_fn, config).unwrap();
+
+        for _ in 0..4 {
+            generator.generate_blocking().unwrap();
+        }
+        assert!(matches!(
+            generator.generate_blocking(),
+            Err(SnowflakeGeneratorError::TimestampError("clock unavailable"))
+        ));
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn test_generate_batch() {
+        let config = SnowflakeConfig::new(41, 10, 4).unwrap();
+        let generator =
+            SnowflakeGenerator::new_with_config(0x10, 0, || Ok(0x1234), config).unwrap();
+        let before = generator.generate().unwrap();
+
+        let batch = generator.generate_batch(40).unwrap();
+        assert_eq!(batch.len(), 40);
+        assert!(batch.windows(2).all(|pair| pair[0] < pair[1]));
+        assert!(before < batch[0]);
+        assert_eq!(config.timestamp_of(batch[39]), 0x1236);
+        assert_eq!(config.sequence_of(batch[39]), 8);
+
+        let after = generator.generate().unwrap();
+        assert!(batch[39] < after);
+        assert!(generator.generate_batch(0).unwrap().is_empty());
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn test_generate_batch_timestamp_overflow() {
+        let config = SnowflakeConfig::new(8, 10, 4).unwrap();
+        let generator = SnowflakeGenerator::new_with_config(0x10, 0, || Ok(0xFD), config).unwrap();
+
+        let result = generator.generate_batch(33);
+        assert!(matches!(
+            result,
+            Err(SnowflakeGeneratorError::TimestampOverflow)
+        ));
+        let batch = generator.generate_batch(32).unwrap();
+        assert_eq!(config.timestamp_of(batch[31]), 0xFE);
+        assert_eq!(config.sequence_of(batch[31]), config.sequence_max);
+
+        // the batch used up the last timestamp, nothing may spill into 0xFF
+        assert!(matches!(
+            generator.generate(),
+            Err(SnowflakeGeneratorError::SequenceOverflow)
+        ));
+    }
+
+    #[test]
+    fn test_clock_went_backwards() {
+        let config = SnowflakeConfig::default()
+            .with_clock_backwards_policy(ClockBackwardsPolicy::Reject { tolerance_ms: 2 });
+        let clock = AtomicU64::new(0x1234);
+        let generator = SnowflakeGenerator::new_with_config(
+            0x10,
+            0,
+            || Ok(clock.load(Ordering::SeqCst)),
+            config,
+        )
+        .unwrap();
+        generator.generate().unwrap();
+
+        clock.store(0x1232, Ordering::SeqCst);
+        let snowflake = generator.generate().unwrap();
+        assert_eq!(config.timestamp_of(snowflake), 0x1234);
+
+        clock.store(0x1231, Ordering::SeqCst);
+        let result = generator.generate();
+        assert!(matches!(
+            result,
+            Err(SnowflakeGeneratorError::ClockWentBackwards)
+        ));
+    }
+
+    #[test]
+    fn test_clock_went_backwards_lenient() {
+        let clock = AtomicU64::new(0x1234);
+        let generator =
+            SnowflakeGenerator::new(0x10, 0, || Ok(clock.load(Ordering::SeqCst))).unwrap();
+        generator.generate().unwrap();
+
+        clock.store(0x1000, Ordering::SeqCst);
+        let snowflake = generator.generate().unwrap();
+        assert_eq!(SnowflakeConfig::default().timestamp_of(snowflake), 0x1234);
+    }
+
+    #[test]
+    fn test_decode_timestamp_micros() {
+        const EPOCH_US: u64 = 1_288_834_974_657_000;
+        let config = SnowflakeConfig::new(51, 1, 11)
+            .unwrap()
+            .with_time_unit(TimeUnit::Micros)
+            .unwrap();
+        let generator =
+            SnowflakeGenerator::new_with_config(0, EPOCH_US, || Ok(EPOCH_US + 1_500_250), config)
+                .unwrap();
+
+        let snowflake = generator.generate().unwrap();
+        assert_eq!(config.timestamp_of(snowflake), 1_500_250);
+        assert_eq!(
+            generator.decode_timestamp(snowflake),
+            core::time::Duration::from_micros(EPOCH_US + 1_500_250)
+        );
+        assert_eq!(generator.decode_timestamp_ms(snowflake), 1_288_834_976_157);
+    }
+
+    #[test]
+    fn test_custom_time_unit() {
+        let sonyflake = TimeUnit::Custom(10_000_000);
+        assert_eq!(
+            sonyflake.to_duration(150),
+            core::time::Duration::from_millis(1500)
+        );
+        assert_eq!(
+            sonyflake.from_duration(core::time::Duration::from_millis(1509)),
+            150
+        );
+
+        let config = SnowflakeConfig::default().with_time_unit(TimeUnit::Custom(0));
+        assert!(matches!(
+            config,
+            Err(SnowflakeGeneratorError::InvalidBitConfig)
+        ));
+    }
+
+    #[test]
+    fn test_overflow_instant() {
+        const TWITTER_EPOCH: u64 = 1_288_834_974_657;
+        let generator = SnowflakeGenerator::new(0x10, TWITTER_EPOCH, || Ok(TWITTER_EPOCH)).unwrap();
+
+        // 2080-07-10T17:30:30.208Z
+        assert_eq!(generator.overflow_instant_ms(), 3_487_858_230_208);
+        #[cfg(feature = "std")]
+        assert_eq!(
+            generator.overflow_systemtime(),
+            Some(std::time::UNIX_EPOCH + std::time::Duration::from_millis(3_487_858_230_208))
+        );
+    }
+
+    #[cfg(all(feature = "std", target_os = "linux"))]
+    #[test]
+    fn test_overflow_systemtime_out_of_range() {
+        // about 1.1e19 seconds, past the i64 seconds a Linux SystemTime holds
+        let config = SnowflakeConfig::default()
+            .with_time_unit(TimeUnit::Custom(5_000_000_000_000_000))
+            .unwrap();
+        let generator = SnowflakeGenerator::new_with_config(0x10, 0, || Ok(0), config).unwrap();
+        assert_eq!(generator.overflow_systemtime(), None);
+    }
+
+    #[test]
+    fn test_display_epoch() {
+        const EPOCH: u64 = 1_000_000;
+        const LAUNCH: u64 = 1_000_500;
+        let generator = SnowflakeGenerator::new(0x10, EPOCH, || Ok(EPOCH + 600))
+            .unwrap()
+            .with_display_epoch(LAUNCH);
+
+        let snowflake = generator.generate().unwrap();
+        assert_eq!(SnowflakeConfig::default().timestamp_of(snowflake), 600);
+        assert_eq!(generator.display_timestamp(snowflake), 100);
+        assert_eq!(generator.display_timestamp(0), -500);
+
+        let mirrored = generator.with_clock(|| Ok(EPOCH + 700)).unwrap();
+        assert_eq!(mirrored.display_timestamp(snowflake), 100);
+    }
+
+    #[test]
+    fn test_display_epoch_micros() {
+        const EPOCH: u64 = 1_000_000_000;
+        let config = SnowflakeConfig::default()
+            .with_time_unit(TimeUnit::Micros)
+            .unwrap();
+        let generator =
+            SnowflakeGenerator::new_with_config(0x10, EPOCH, || Ok(EPOCH + 5_000), config).unwrap();
+
+        let snowflake = generator.generate().unwrap();
+        assert_eq!(generator.display_timestamp(snowflake), 5);
+
+        let generator = generator.with_display_epoch(EPOCH + 7_500);
+        assert_eq!(generator.display_timestamp(snowflake), -2);
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn test_default_layout() {
+        let layout = SnowflakeConfig::default().layout();
+        assert_eq!(
+            layout,
+            vec![
+                FieldLayout {
+                    name: "timestamp",
+                    shift: 22,
+                    width: 41
+                },
+                FieldLayout {
+                    name: "machine_id",
+                    shift: 12,
+                    width: 10
+                },
+                FieldLayout {
+                    name: "sequence",
+                    shift: 0,
+                    width: 12
+                },
+            ]
+        );
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn test_custom_layout() {
+        let config = SnowflakeConfig::new(42, 5, 8).unwrap();
+        let layout = config.layout();
+
+        let fields = layout
+            .iter()
+            .map(|field| (field.name, field.shift, field.width))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            fields,
+            vec![
+                ("timestamp", 13, 42),
+                ("machine_id", 8, 5),
+                ("sequence", 0, 8)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_era_rollover() {
+        let config = SnowflakeConfig::new(4, 10, 12)
+            .unwrap()
+            .with_era_bits(2)
+            .unwrap();
+        let clock = AtomicU64::new(15);
+        let generator = SnowflakeGenerator::new_with_config(
+            0x10,
+            0,
+            || Ok(clock.load(Ordering::SeqCst)),
+            config,
+        )
+        .unwrap();
+
+        let before = generator.generate().unwrap();
+        clock.store(16, Ordering::SeqCst);
+        let after = generator.generate().unwrap();
+
+        assert_eq!(before, 0xF10000);
+        assert_eq!(after, 0x1010000);
+        assert!(after > before);
+        assert_eq!(config.timestamp_of(after), 16);
+
+        clock.store(64, Ordering::SeqCst);
+        assert!(matches!(
+            generator.generate(),
+            Err(SnowflakeGeneratorError::TimestampOverflow)
+        ));
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn test_era_layout() {
+        let config = SnowflakeConfig::default().with_era_bits(3).unwrap();
+        let fields = config
+            .layout()
+            .iter()
+            .map(|field| (field.name, field.shift, field.width))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            fields,
+            vec![
+                ("era", 60, 3),
+                ("timestamp", 19, 41),
+                ("machine_id", 12, 7),
+                ("sequence", 0, 12)
+            ]
+        );
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn test_datacenter_layout() {
+        let config = SnowflakeConfig::default()
+            .with_datacenter_bits(4, 6)
+            .unwrap()
+            .with_machine_sequence_order(MachineSequenceOrder::MachineBelowSequence);
+        let fields = config
+            .layout()
+            .iter()
+            .map(|field| (field.name, field.shift, field.width))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            fields,
+            vec![
+                ("timestamp", 22, 41),
+                ("sequence", 10, 12),
+                ("datacenter_id", 6, 4),
+                ("worker_id", 0, 6)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_era_needs_machine_id_bits() {
+        let config = SnowflakeConfig::default().with_era_bits(10);
+        assert!(matches!(
+            config,
+            Err(SnowflakeGeneratorError::InvalidBitConfig)
+        ));
+    }
+
+    #[test]
+    fn test_datacenter_and_worker() {
+        let config = SnowflakeConfig::default()
+            .with_datacenter_bits(5, 5)
+            .unwrap();
+        let generator =
+            SnowflakeGenerator::new_with_datacenter(0x13, 0x0A, 0, || Ok(0x1234), config).unwrap();
+
+        let snowflake = generator.generate().unwrap();
+        assert_eq!(snowflake, config.pack(0x1234, 0x13 << 5 | 0x0A, 0));
+        assert_eq!(config.datacenter_id_of(snowflake), 0x13);
+        assert_eq!(config.worker_id_of(snowflake), 0x0A);
+
+        let result = SnowflakeGenerator::new_with_datacenter(0, 0x20, 0, || Ok(0x1234), config);
+        assert!(matches!(
+            result,
+            Err(SnowflakeGeneratorError::MachineIdOverflow)
+        ));
+        let config = config.with_machine_id_policy(MachineIdPolicy::Mask);
+        assert_eq!(config.machine_id_from_parts(0x21, 0x3F).unwrap(), 0x3F);
+    }
+
+    #[test]
+    fn test_datacenter_bits_must_add_up() {
+        let config = SnowflakeConfig::default();
+        assert!(matches!(
+            config.with_datacenter_bits(5, 4),
+            Err(SnowflakeGeneratorError::InvalidBitConfig)
+        ));
+        assert_eq!(config.worker_id_of(config.pack(0, 0x3FF, 0)), 0x3FF);
+
+        let config = config.with_era_bits(2).unwrap();
+        let split = config.with_datacenter_bits(3, 5).unwrap();
+        assert!(matches!(
+            split.with_era_bits(1),
+            Err(SnowflakeGeneratorError::InvalidBitConfig)
+        ));
+    }
+
+    #[test]
+    fn test_generate_with_flavor() {
+        let config = SnowflakeConfig::new(41, 10, 12)
+            .unwrap()
+            .with_flavor_bits(1)
+            .unwrap();
+        let generator =
+            SnowflakeGenerator::new_with_config(0x10, 0, || Ok(0x1234), config).unwrap();
+
+        let plain = generator.generate().unwrap();
+        let flavored = generator.generate_with_flavor(1).unwrap();
+        assert_eq!(plain, 0x91A010000);
+        assert_eq!(flavored, 0x91A410001);
+        assert_eq!(config.flavor_of(plain), 0);
+        assert_eq!(config.flavor_of(flavored), 1);
+        assert_eq!(config.timestamp_of(flavored), 0x1234);
+
+        let result = generator.generate_with_flavor(2);
+        assert!(matches!(
+            result,
+            Err(SnowflakeGeneratorError::FlavorOverflow)
+        ));
+    }
+
+    #[test]
+    fn test_flavor_bits_in_bit_sum() {
+        let config = SnowflakeConfig::default().with_flavor_bits(2);
+        assert!(matches!(
+            config,
+            Err(SnowflakeGeneratorError::InvalidBitConfig)
+        ));
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn test_machine_below_sequence() {
+        let config = SnowflakeConfig::default()
+            .with_machine_sequence_order(MachineSequenceOrder::MachineBelowSequence);
+        let generator =
+            SnowflakeGenerator::new_with_config(0x10, 0, || Ok(0x1234), config).unwrap();
+
+        generator.generate().unwrap();
+        let snowflake = generator.generate().unwrap();
+        assert_eq!(snowflake, 0x48D000410);
+        assert_eq!(config.timestamp_of(snowflake), 0x1234);
+        assert_eq!(config.machine_id_of(snowflake), 0x10);
+        assert_eq!(config.sequence_of(snowflake), 1);
+
+        let layout = config.layout();
+        assert_eq!(layout[1].name, "sequence");
+        assert_eq!(layout[1].shift, 10);
+        assert_eq!(layout[2].name, "machine_id");
+        assert_eq!(layout[2].shift, 0);
+    }
+
+    #[test]
+    fn test_state() {
+        let config = SnowflakeConfig::new(42, 5, 2).unwrap();
+        let clock = AtomicU64::new(0x1234);
+        let generator = SnowflakeGenerator::new_with_config(
+            1,
+            0x1000,
+            || Ok(clock.load(Ordering::SeqCst)),
+            config,
+        )
+        .unwrap();
+        assert_eq!(generator.state(), (0x234, 0));
+
+        generator.generate().unwrap();
+        generator.generate().unwrap();
+        assert_eq!(generator.state(), (0x234, 2));
+        assert_eq!(generator.state(), (0x234, 2));
+
+        clock.store(0x1235, Ordering::SeqCst);
+        assert_eq!(generator.state(), (0x234, 2));
+        for _ in 0..4 {
+            generator.generate().unwrap();
+        }
+        assert_eq!(generator.state(), (0x235, 4));
+    }
+
+    #[rstest]
+    #[case(FieldOrder::TimestampMachineSequence)]
+    #[case(FieldOrder::TimestampSequenceMachine)]
+    #[case(FieldOrder::MachineTimestampSequence)]
+    #[case(FieldOrder::MachineSequenceTimestamp)]
+    #[case(FieldOrder::SequenceTimestampMachine)]
+    #[case(FieldOrder::SequenceMachineTimestamp)]
+    fn test_field_order(#[case] field_order: FieldOrder) {
+        let config = SnowflakeConfig::new(40, 10, 12)
+            .unwrap()
+            .with_era_bits(1)
+            .unwrap()
+            .with_flavor_bits(1)
+            .unwrap()
+            .with_field_order(field_order);
+        let clock = AtomicU64::new(0x1234);
+        let generator = SnowflakeGenerator::new_with_config(
+            0x1A,
+            0,
+            || Ok(clock.load(Ordering::SeqCst)),
+            config,
+        )
+        .unwrap();
+
+        generator.generate().unwrap();
+        let snowflake = generator.generate_with_flavor(1).unwrap();
+        assert_eq!(
+            config.decode(snowflake),
+            DecodedSnowflake {
+                timestamp: 0x1234,
+                machine_id: 0x1A,
+                sequence: 1,
+            }
+        );
+        assert_eq!(config.flavor_of(snowflake), 1);
+
+        clock.store(1 << 40 | 0x1235, Ordering::SeqCst);
+        let snowflake = generator.generate().unwrap();
+        assert_eq!(config.timestamp_of(snowflake), 1 << 40 | 0x1235);
+        assert_eq!(config.sequence_of(snowflake), 0);
+
+        let all_set = config
+            .encode(
+                config.timestamp_max(),
+                config.machine_id_max(),
+                config.sequence_max(),
+            )
+            .unwrap()
+            | 1 << config.flavor_shift();
+        assert_eq!(all_set, build_mask(63));
     }
 
-    pub(crate) fn timestamp_shift(&self) -> u64 {
-        self.machine_id_bits + self.sequence_bits
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn test_field_order_layout() {
+        let config = SnowflakeConfig::default()
+            .with_tombstone_bit(true)
+            .unwrap()
+            .with_field_order(FieldOrder::SequenceTimestampMachine);
+        let fields = config
+            .layout()
+            .iter()
+            .map(|field| (field.name, field.shift, field.width))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            fields,
+            vec![
+                ("tombstone", 63, 1),
+                ("sequence", 51, 12),
+                ("timestamp", 10, 41),
+                ("machine_id", 0, 10)
+            ]
+        );
+        assert_eq!(config.pack(0, 0, 1), 1 << 51);
     }
 
-    fn validate_config(
-        machine_id_bits: u64,
-        sequence_bits: u64,
-        timestamp_bits: u64,
-    ) -> Result<(), SnowflakeGeneratorError> {
-        let bit_sum = timestamp_bits + machine_id_bits + sequence_bits;
-        if bit_sum > 64 {
-            return Err(SnowflakeGeneratorError::InvalidBitConfig);
-        }
+    #[test]
+    fn test_js_safe() {
+        let config = SnowflakeConfig::js_safe(41, 4, 8).unwrap();
+        assert!(config.is_js_safe());
+        assert!(!SnowflakeConfig::default().is_js_safe());
 
-        if machine_id_bits == 0 || sequence_bits == 0 || timestamp_bits == 0 {
+        let max_id = config.pack(u64::MAX, u64::MAX, u64::MAX);
+        // Number.MAX_SAFE_INTEGER
+        assert_eq!(max_id, 9_007_199_254_740_991);
+
+        let config = SnowflakeConfig::js_safe(41, 5, 8);
+        assert!(matches!(
+            config,
             Err(SnowflakeGeneratorError::InvalidBitConfig)
-        } else {
-            Ok(())
-        }
+        ));
     }
-}
 
-impl Default for SnowflakeConfig {
-    fn default() -> Self {
-        Self::new(
-            DEFAULT_TIMESTAMP_BITS,
-            DEFAULT_MACHINE_ID_BITS,
-            DEFAULT_SEQUENCE_ID_BITS,
+    #[test]
+    fn test_generate_detailed() {
+        let clock = AtomicU64::new(0x1234);
+        let generator =
+            SnowflakeGenerator::new(0x10, 0, || Ok(clock.load(Ordering::SeqCst))).unwrap();
+        generator.generate().unwrap();
+
+        // the clock steps back, so the last timestamp used is kept
+        clock.store(0x1000, Ordering::SeqCst);
+        let generated = generator.generate_detailed().unwrap();
+        assert_eq!(
+            generated,
+            GeneratedSnowflake {
+                id: SnowflakeConfig::default().pack(0x1234, 0x10, 1),
+                timestamp: 0x1234,
+                machine_id: 0x10,
+                sequence: 1,
+            }
+        );
+    }
+
+    #[test]
+    fn test_generate_i64() {
+        let config = SnowflakeConfig::i64_safe(41, 10, 12).unwrap();
+        let generator =
+            SnowflakeGenerator::new_with_config(0x3FF, 0, || Ok(0x1FF_FFFF_FFFE), config).unwrap();
+        let snowflake = generator.generate_i64().unwrap();
+        assert_eq!(snowflake, 0x7FFF_FFFF_FFBF_F000);
+
+        let config = SnowflakeConfig::new(42, 10, 12).unwrap();
+        assert!(!config.is_i64_safe());
+        assert!(matches!(
+            SnowflakeConfig::i64_safe(42, 10, 12),
+            Err(SnowflakeGeneratorError::InvalidBitConfig)
+        ));
+        let generator = SnowflakeGenerator::new_with_config(0, 0, || Ok(0), config).unwrap();
+        assert!(matches!(
+            generator.generate_i64(),
+            Err(SnowflakeGeneratorError::InvalidBitConfig)
+        ));
+    }
+
+    #[test]
+    fn test_generate_with_ordinal() {
+        let generator = SnowflakeGenerator::new(0x10, 0, || Ok(0x1234)).unwrap();
+        let other = SnowflakeGenerator::new(0x11, 0, || Ok(0x1234)).unwrap();
+
+        let (snowflake, first) = generator.generate_with_ordinal().unwrap();
+        let (_, second) = other.generate_with_ordinal().unwrap();
+        let (next, third) = generator.generate_with_ordinal().unwrap();
+        assert_eq!(snowflake, 0x48D010000);
+        assert_eq!(next, 0x48D010001);
+        assert!(first < second && second < third);
+    }
+
+    #[test]
+    fn test_min_timestamp() {
+        let clock = AtomicU64::new(0x1234);
+        let config = SnowflakeConfig::default().with_min_timestamp_ms(0x1000);
+        let generator = SnowflakeGenerator::new_with_config(
+            0x10,
+            0,
+            || Ok(clock.load(Ordering::SeqCst)),
+            config,
         )
-        .expect("Default values incorrect")
+        .unwrap();
+        assert_eq!(generator.generate().unwrap(), 0x48D010000);
+
+        clock.store(0xFFF, Ordering::SeqCst);
+        let result = generator.generate();
+        assert!(matches!(
+            result,
+            Err(SnowflakeGeneratorError::TimestampBelowFloor)
+        ));
+
+        let result = SnowflakeGenerator::new_with_config(0x10, 0, || Ok(0xFFF), config);
+        assert!(matches!(
+            result,
+            Err(SnowflakeGeneratorError::TimestampBelowFloor)
+        ));
     }
-}
 
-pub(crate) fn build_mask(bits: u64) -> u64 {
-    (1 << bits) - 1
-}
+    #[test]
+    fn test_all_valid_ids_round_trip() {
+        let config = SnowflakeConfig::new(3, 2, 2).unwrap();
+        let ids = config.all_valid_ids().collect::<Vec<_>>();
+        assert_eq!(ids.len(), 112);
 
-pub(crate) fn calc_max(bits: u64) -> u64 {
-    2u64.pow(bits as u32) - 1
-}
+        for (expected, id) in ids.into_iter().enumerate() {
+            // with nothing but the three fields, every 7 bit value below the reserved
+            // timestamp is valid
+            assert_eq!(id, expected as u64);
+            let repacked = config.pack(
+                config.timestamp_of(id),
+                config.machine_id_of(id),
+                config.sequence_of(id),
+            );
+            assert_eq!(repacked, id);
+        }
+    }
 
-#[cfg(test)]
-mod test {
-    use std::sync::{
-        atomic::{AtomicU64, Ordering},
-        Arc,
-    };
+    #[test]
+    fn test_all_valid_ids_are_valid() {
+        let config = SnowflakeConfig::new(2, 1, 1).unwrap();
+        assert_eq!(config.all_valid_ids().count(), 12);
+        assert!(config.all_valid_ids().all(|id| config.is_valid(id)));
 
-    use rstest::rstest;
+        let config = config.with_min_timestamp_ms(1);
+        assert_eq!(config.all_valid_ids().count(), 8);
+        assert!(config.all_valid_ids().all(|id| config.is_valid(id)));
+    }
 
-    use super::*;
+    #[test]
+    fn test_generate_with_saturation() {
+        let config = SnowflakeConfig::new(42, 5, 2).unwrap();
+        let generator =
+            SnowflakeGenerator::new_with_config(0x10, 0, || Ok(0x1234), config).unwrap();
+
+        let saturation = (0..4)
+            .map(|_| generator.generate_with_saturation().unwrap().1)
+            .collect::<Vec<_>>();
+        assert_eq!(saturation, vec![0.0, 1.0 / 3.0, 2.0 / 3.0, 1.0]);
+    }
 
     #[test]
-    fn test_timestamp_generation() {
-        const TIMESTAMP: u64 = 0x1234u64;
-        let timestamp_fn = || Ok(TIMESTAMP);
-        let machine_id = 0x10u32;
-        let epoch = 0u64;
+    fn test_epoch_in_future() {
+        let result = SnowflakeGenerator::new(0x10, 0x2000, || Ok(0x1234));
+        assert!(matches!(
+            result,
+            Err(SnowflakeGeneratorError::EpochInFuture)
+        ));
 
-        let generator = SnowflakeGenerator::new(machine_id, epoch, timestamp_fn).unwrap();
-        let snowflake = generator.generate().unwrap();
-        assert_eq!(snowflake, 0x48D010000);
+        let clock = AtomicU64::new(0x2000);
+        let generator =
+            SnowflakeGenerator::new(0x10, 0x2000, || Ok(clock.load(Ordering::SeqCst))).unwrap();
+        clock.store(0x1fff, Ordering::SeqCst);
+        assert!(matches!(
+            generator.generate(),
+            Err(SnowflakeGeneratorError::EpochInFuture)
+        ));
+    }
 
-        let snowflake = generator.generate().unwrap();
-        assert_eq!(snowflake, 0x48D010001);
+    #[test]
+    fn test_new_with_state() {
+        let config = SnowflakeConfig::new(42, 5, 2).unwrap();
+        let generator =
+            SnowflakeGenerator::new_with_config(0x10, 0, || Ok(0x1234), config).unwrap();
+        let saved = (0..2)
+            .map(|_| generator.generate_detailed().unwrap())
+            .last()
+            .unwrap();
+
+        let resumed = SnowflakeGenerator::new_with_state(
+            0x10,
+            0,
+            || Ok(0x1234),
+            config,
+            saved.timestamp,
+            saved.sequence,
+        )
+        .unwrap();
+        let snowflake = resumed.generate().unwrap();
+        assert!(snowflake > saved.id);
+        assert_eq!(snowflake, config.pack(0x1234, 0x10, 2));
+
+        let resumed = SnowflakeGenerator::new_with_state(
+            0x10,
+            0,
+            || Ok(0x1234),
+            config,
+            0x1234,
+            config.sequence_max,
+        )
+        .unwrap();
+        assert!(matches!(
+            resumed.generate(),
+            Err(SnowflakeGeneratorError::SequenceOverflow)
+        ));
+
+        let result = SnowflakeGenerator::new_with_state(0x10, 0, || Ok(0x1234), config, 0x1234, 4);
+        assert!(matches!(
+            result,
+            Err(SnowflakeGeneratorError::SequenceOverflow)
+        ));
     }
 
+    #[cfg(feature = "alloc")]
     #[test]
-    fn test_sequence_overflow() {
-        const TIMESTAMP: u64 = 0x1234u64;
-        let timestamp_fn = || Ok(TIMESTAMP);
-        let machine_id = 0x10u32;
-        let epoch = 0u64;
-        let sequence_id_max = SnowflakeConfig::default().sequence_max + 1;
+    fn test_on_sequence_pressure() {
+        let config = SnowflakeConfig::new(42, 5, 2).unwrap();
+        let pressure = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let recorded = pressure.clone();
+        let generator = SnowflakeGenerator::new_with_config(0x10, 0, || Ok(0x1234), config)
+            .unwrap()
+            .on_sequence_pressure(0.5, move |used, max| {
+                recorded.lock().unwrap().push((used, max))
+            });
 
-        let generator = SnowflakeGenerator::new(machine_id, epoch, timestamp_fn).unwrap();
-        // iterate over generation until right before sequence overflow
-        for _ in 0..sequence_id_max {
+        for _ in 0..4 {
             generator.generate().unwrap();
         }
+        assert!(generator.generate().is_err());
+        assert_eq!(*pressure.lock().unwrap(), [(2, 3), (3, 3)]);
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn test_on_sequence_pressure_never_exceeded() {
+        let config = SnowflakeConfig::new(42, 5, 2).unwrap();
+        for high_water in [1.0, f32::NAN] {
+            let calls = Arc::new(AtomicU64::new(0));
+            let recorded = calls.clone();
+            let generator = SnowflakeGenerator::new_with_config(0x10, 0, || Ok(0x1234), config)
+                .unwrap()
+                .on_sequence_pressure(high_water, move |_, _| {
+                    recorded.fetch_add(1, Ordering::SeqCst);
+                });
+
+            for _ in 0..4 {
+                generator.generate().unwrap();
+            }
+            assert_eq!(calls.load(Ordering::SeqCst), 0);
+        }
+    }
+
+    #[test]
+    fn test_sequence_remaining() {
+        let config = SnowflakeConfig::new(42, 5, 2).unwrap();
+        let clock = AtomicU64::new(0x1234);
+        let generator = SnowflakeGenerator::new_with_config(
+            0x10,
+            0,
+            || Ok(clock.load(Ordering::SeqCst)),
+            config,
+        )
+        .unwrap();
+        assert_eq!(generator.sequence_remaining(), 4);
+
+        for remaining in (0..4).rev() {
+            generator.generate().unwrap();
+            assert_eq!(generator.sequence_remaining(), remaining);
+        }
+        assert!(generator.generate().is_err());
+        assert_eq!(generator.sequence_remaining(), 0);
+
+        clock.store(0x1235, Ordering::SeqCst);
+        assert_eq!(generator.sequence_remaining(), 4);
+    }
+
+    #[rstest]
+    #[case(SnowflakeConfig::default())]
+    #[case(SnowflakeConfig::new(42, 5, 8).unwrap())]
+    #[case(SnowflakeConfig::new(40, 12, 10).unwrap().with_flavor_bits(2).unwrap())]
+    fn test_decode_round_trip(#[case] config: SnowflakeConfig) {
+        let generator =
+            SnowflakeGenerator::new_with_config(0x1A, 0, || Ok(0x1234), config).unwrap();
+        generator.generate().unwrap();
+
+        let snowflake = generator.generate().unwrap();
+        assert_eq!(
+            config.decode(snowflake),
+            DecodedSnowflake {
+                timestamp: 0x1234,
+                machine_id: 0x1A,
+                sequence: 1,
+            }
+        );
+    }
+
+    #[test]
+    fn test_encode() {
+        let config = SnowflakeConfig::new(42, 5, 8).unwrap();
+        let snowflake = config.encode(0x1234, 0x1A, 0xFF).unwrap();
+        assert_eq!(
+            config.decode(snowflake),
+            DecodedSnowflake {
+                timestamp: 0x1234,
+                machine_id: 0x1A,
+                sequence: 0xFF,
+            }
+        );
 
-        let res = generator.generate();
         assert!(matches!(
-            res,
+            config.encode(1 << 42, 0, 0),
+            Err(SnowflakeGeneratorError::TimestampOverflow)
+        ));
+        assert!(matches!(
+            config.encode(0, 0x20, 0),
+            Err(SnowflakeGeneratorError::MachineIdOverflow)
+        ));
+        assert!(matches!(
+            config.encode(0, 0, 0x100),
             Err(SnowflakeGeneratorError::SequenceOverflow)
         ));
     }
 
+    #[cfg(feature = "alloc")]
     #[test]
-    fn test_timestamp_overflow() {
-        let timestamp: u64 = SnowflakeConfig::default().timestamp_max + 1;
-        let call_count = Arc::new(AtomicU64::new(0));
-        let timestamp_fn = || {
-            let count = call_count.fetch_add(1, Ordering::SeqCst);
-            if count < 1 {
-                Ok(0)
-            } else {
-                Ok(timestamp)
-            }
-        };
-        let machine_id = 0x10u32;
-        let epoch = 0u64;
+    fn test_generate_hex_and_decimal() {
+        let config = SnowflakeConfig::new(20, 5, 8).unwrap();
+        let generator =
+            SnowflakeGenerator::new_with_config(0x10, 0, || Ok(0x1234), config).unwrap();
 
-        let generator = SnowflakeGenerator::new(machine_id, epoch, timestamp_fn).unwrap();
-        let result = generator.generate();
+        let hex = generator.generate_hex().unwrap();
+        assert_eq!(hex, "002469000");
+        assert_eq!(
+            decode_hex(&hex, &config).unwrap(),
+            config.pack(0x1234, 0x10, 0)
+        );
+        assert_eq!(
+            generator.generate_decimal().unwrap(),
+            config.pack(0x1234, 0x10, 1).to_string()
+        );
+    }
+
+    #[test]
+    fn test_new_unchecked() {
+        const CONFIG: SnowflakeConfig = SnowflakeConfig::new_unchecked(41, 10, 12);
+        const SEQUENCE_MAX: u64 = CONFIG.sequence_max();
+        assert_eq!(SEQUENCE_MAX, 4095);
+
+        let config = SnowflakeConfig::default();
+        assert_eq!(CONFIG.timestamp_max(), config.timestamp_max());
+        assert_eq!(CONFIG.machine_id_max(), config.machine_id_max());
+        assert_eq!(CONFIG.pack(0x1234, 0x10, 5), config.pack(0x1234, 0x10, 5));
+    }
+
+    #[test]
+    fn test_reserved_bits() {
+        let config = SnowflakeConfig::new(40, 10, 12)
+            .unwrap()
+            .with_reserved_bits(2)
+            .unwrap();
+        assert!(config.is_i64_safe());
         assert!(matches!(
-            result,
+            config.with_tombstone_bit(true),
+            Err(SnowflakeGeneratorError::InvalidBitConfig)
+        ));
+        assert!(matches!(
+            SnowflakeConfig::default().with_reserved_bits(2),
+            Err(SnowflakeGeneratorError::InvalidBitConfig)
+        ));
+
+        let generator = SnowflakeGenerator::new_with_config(
+            0x3FF,
+            0,
+            || Ok(config.timestamp_max() - 1),
+            config,
+        )
+        .unwrap();
+        let snowflake = generator.generate().unwrap();
+        assert_eq!(snowflake >> 62, 0);
+        assert_eq!(config.decode(snowflake).machine_id, 0x3FF);
+        assert!(!config.is_valid(snowflake | 1 << 62));
+    }
+
+    #[test]
+    fn test_split_machine_space() {
+        let config = SnowflakeConfig::new(42, 5, 4).unwrap();
+        let snowflakes = (0..3)
+            .map(|instance| {
+                let (machine_id, instance_config) =
+                    config.split_machine_space(0x10, 3, instance).unwrap();
+                assert_eq!(instance_config.sequence_max(), 3);
+                let generator = SnowflakeGenerator::new_with_config(
+                    machine_id,
+                    0,
+                    || Ok(0x1234),
+                    instance_config,
+                )
+                .unwrap();
+                generator.generate().unwrap()
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            snowflakes,
+            [0, 1, 2].map(|instance| config.pack(0x1234, 0x10, instance << 2))
+        );
+
+        assert!(matches!(
+            config.split_machine_space(0x10, 3, 3),
+            Err(SnowflakeGeneratorError::MachineIdOverflow)
+        ));
+        assert!(matches!(
+            config.split_machine_space(0x10, 16, 0),
+            Err(SnowflakeGeneratorError::InvalidBitConfig)
+        ));
+        assert!(matches!(
+            config
+                .with_field_order(FieldOrder::TimestampSequenceMachine)
+                .split_machine_space(0x10, 2, 0),
+            Err(SnowflakeGeneratorError::InvalidBitConfig)
+        ));
+    }
+
+    #[test]
+    fn test_config_eq_and_hash() {
+        let config = SnowflakeConfig::new(41, 10, 12).unwrap();
+        assert_eq!(config, SnowflakeConfig::default());
+        assert_ne!(
+            config,
+            config.with_field_order(FieldOrder::TimestampSequenceMachine)
+        );
+        assert_ne!(config, SnowflakeConfig::new(42, 10, 11).unwrap());
+
+        let decoded = [0x1234, 0x1234, 0x1235]
+            .map(|timestamp| config.decode(config.pack(timestamp, 0x10, 0)))
+            .into_iter()
+            .collect::<std::collections::HashSet<_>>();
+        assert_eq!(decoded.len(), 2);
+    }
+
+    #[test]
+    fn test_generate_unchecked() {
+        let config = SnowflakeConfig::new(42, 5, 2).unwrap();
+        let clock = AtomicU64::new(0x1234);
+        let generator = SnowflakeGenerator::new_with_config(
+            0x10,
+            0,
+            || Ok(clock.load(Ordering::SeqCst)),
+            config,
+        )
+        .unwrap();
+        assert_eq!(
+            generator.generate_unchecked().unwrap(),
+            config.pack(0x1234, 0x10, 0)
+        );
+
+        clock.store(config.timestamp_max() + 2, Ordering::SeqCst);
+        assert!(matches!(
+            generator.generate(),
             Err(SnowflakeGeneratorError::TimestampOverflow)
         ));
+        let first = generator.generate_unchecked().unwrap();
+        let second = generator.generate_unchecked().unwrap();
+        assert_ne!(first, second);
+        assert_eq!(first, config.pack(0x1234, 0x10, 1));
+        assert_eq!(second, config.pack(0x1234, 0x10, 2));
+        generator.generate_unchecked().unwrap();
+        assert!(matches!(
+            generator.generate_unchecked(),
+            Err(SnowflakeGeneratorError::SequenceOverflow)
+        ));
     }
 
     #[test]
-    fn test_timestamp_failure() {
-        let timestamp_fn = || Err("Timestamp error");
-        let machine_id = 0x10u32;
-        let epoch = 0u64;
+    fn test_validate() {
+        let config = SnowflakeConfig::new(42, 5, 8)
+            .unwrap()
+            .with_min_timestamp_ms(0x1000);
+        assert!(config.is_valid(config.pack(0x1234, 0x1A, 0xFF)));
 
-        let generator = SnowflakeGenerator::new(machine_id, epoch, timestamp_fn);
         assert!(matches!(
-            generator,
-            Err(SnowflakeGeneratorError::TimestampError("Timestamp error"))
+            config.validate(config.pack(0x1234, 0, 0) | 1 << 55),
+            Err(SnowflakeGeneratorError::InvalidEncoding)
+        ));
+        assert!(matches!(
+            config.validate(config.pack(config.timestamp_max, 0, 0)),
+            Err(SnowflakeGeneratorError::TimestampOverflow)
         ));
+        assert!(matches!(
+            config.validate(config.pack(0xFFF, 0, 0)),
+            Err(SnowflakeGeneratorError::TimestampBelowFloor)
+        ));
+
+        let prefixed = SnowflakeConfig::new(40, 5, 8)
+            .unwrap()
+            .with_prefix(2, 0b10)
+            .unwrap();
+        assert!(prefixed.is_valid(prefixed.pack(0x1234, 0, 0)));
+        assert!(!prefixed.is_valid(config.pack(0x1234, 0, 0)));
     }
 
     #[test]
-    fn test_invalid_config_too_many_bits() {
-        let config = SnowflakeConfig::new(41, 10, 24);
+    fn test_limits() {
+        let config = SnowflakeConfig::default();
+        assert_eq!(config.sequence_max(), 4095);
+        assert_eq!(config.timestamp_max(), (1 << 41) - 1);
+        assert_eq!(config.machine_id_max(), 1023);
+
+        let config = SnowflakeConfig::new(40, 12, 10)
+            .unwrap()
+            .with_era_bits(2)
+            .unwrap();
+        assert_eq!(config.timestamp_max(), (1 << 42) - 1);
+        assert_eq!(config.machine_id_max(), 1023);
+    }
+
+    #[test]
+    fn test_discord_config() {
+        let config = SnowflakeConfig::discord();
+        assert_eq!(config.timestamp_mask, (1 << 42) - 1);
+        assert_eq!(config.machine_id_mask, 0x3FF);
+        assert_eq!(config.sequence_max, 4095);
+
+        // from the Discord docs, worker 1, process 0, sequence 7
+        let snowflake = 175_928_847_299_117_063;
+        assert_eq!(config.timestamp_of(snowflake), 41_944_705_796);
+        assert_eq!(config.machine_id_of(snowflake), 1 << 5);
+        assert_eq!(config.sequence_of(snowflake), 7);
+    }
+
+    #[test]
+    fn test_instagram_config() {
+        let config = SnowflakeConfig::instagram();
+        assert_eq!(config.timestamp_mask, (1 << 41) - 1);
+        assert_eq!(config.machine_id_mask, 0x1FFF);
+        assert_eq!(config.sequence_max, 1023);
+        assert_eq!(config.pack(1, 0, 0), 1 << 23);
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn test_prefix() {
+        let config = SnowflakeConfig::new(40, 10, 12)
+            .unwrap()
+            .with_prefix(2, 0b10)
+            .unwrap();
+        let generator =
+            SnowflakeGenerator::new_with_config(0x10, 0, || Ok(0x1234), config).unwrap();
+
+        let snowflake = generator.generate().unwrap();
+        assert_eq!(snowflake, 0x8000_0004_8D01_0000);
+        assert_eq!(config.timestamp_of(snowflake), 0x1234);
+        assert_eq!(config.machine_id_of(snowflake), 0x10);
+        assert_eq!(
+            config.layout()[0],
+            FieldLayout {
+                name: "prefix",
+                shift: 62,
+                width: 2,
+            }
+        );
+    }
+
+    #[test]
+    fn test_invalid_prefix() {
+        let config = SnowflakeConfig::default().with_prefix(2, 0b10);
+        assert!(matches!(
+            config,
+            Err(SnowflakeGeneratorError::InvalidBitConfig)
+        ));
+
+        let config = SnowflakeConfig::new(40, 10, 12)
+            .unwrap()
+            .with_prefix(2, 0b100);
         assert!(matches!(
             config,
             Err(SnowflakeGeneratorError::InvalidBitConfig)
@@ -0,0 +1,154 @@
+//! Helpers for deriving machine IDs from host or workload identifiers.
+
+use crate::SnowflakeConfig;
+#[cfg(all(feature = "std", target_os = "linux"))]
+use crate::SnowflakeGeneratorError;
+
+const FNV_OFFSET_BASIS: u64 = 0xCBF2_9CE4_8422_2325;
+const FNV_PRIME: u64 = 0x0000_0100_0000_01B3;
+
+/// Derive a machine ID by hashing a UID, such as a Kubernetes pod UID.
+///
+/// The UID is hashed with FNV-1a and masked to the machine ID bits of the config,
+/// so the same UID always maps to the same machine ID, across processes and
+/// releases.
+///
+/// Different UIDs can map to the same machine ID. With `n` pods and `b` machine ID
+/// bits, the chance that any two collide is roughly `n^2 / 2^(b + 1)`: for the
+/// default 10 bits that is already 50% at around 38 pods. Only rely on this when
+/// the machine ID space is several orders of magnitude larger than the square of
+/// the pod count, and assign machine IDs explicitly otherwise.
+pub fn machine_id_from_uid(uid: &str, config: &SnowflakeConfig) -> u32 {
+    let hash = uid.bytes().fold(FNV_OFFSET_BASIS, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(FNV_PRIME)
+    });
+
+    (hash & config.machine_id_mask) as u32
+}
+
+/// Derive a machine ID by hashing the hostname. Linux only.
+///
+/// The hostname is read from `/proc/sys/kernel/hostname`, or `/etc/hostname` where
+/// that doesn't exist, and hashed like [machine_id_from_uid]. As there, the config
+/// is taken rather than a bit count, so the ID is masked to exactly the machine ID
+/// bits the config packs. This is best effort:
+/// hostnames that differ can still collide, with the same odds as UIDs, so it only
+/// saves plumbing an ID through on fleets that are small compared to the machine ID
+/// space. Returns [SnowflakeGeneratorError::MachineIdUnavailable] if the hostname
+/// can't be read.
+#[cfg(all(feature = "std", target_os = "linux"))]
+pub fn machine_id_from_hostname(config: &SnowflakeConfig) -> Result<u32, SnowflakeGeneratorError> {
+    ["/proc/sys/kernel/hostname", "/etc/hostname"]
+        .into_iter()
+        .filter_map(|path| std::fs::read_to_string(path).ok())
+        .map(|hostname| hostname.trim().to_owned())
+        .find(|hostname| !hostname.is_empty())
+        .map(|hostname| machine_id_from_uid(&hostname, config))
+        .ok_or(SnowflakeGeneratorError::MachineIdUnavailable)
+}
+
+/// Derive a machine ID from the MAC address of a network interface. Linux only.
+///
+/// This takes the config rather than a bit count, as [machine_id_from_hostname]
+/// does. The interfaces are read from `/sys/class/net`, and the first one by name with a
+/// MAC address other than all zeros is used, which skips the loopback interface.
+/// The machine ID is the low bits of the address, rather than a hash, because
+/// vendors hand out the low bits of a batch of NICs in sequence, so hosts bought
+/// together get distinct machine IDs. This is best effort all the same: addresses
+/// can share their low bits, and the interface picked can change when interfaces
+/// are added or renamed. Returns [SnowflakeGeneratorError::MachineIdUnavailable] if
+/// no MAC address can be read.
+#[cfg(all(feature = "std", target_os = "linux"))]
+pub fn machine_id_from_mac(config: &SnowflakeConfig) -> Result<u32, SnowflakeGeneratorError> {
+    let mut interfaces = std::fs::read_dir("/sys/class/net")
+        .map_err(|_| SnowflakeGeneratorError::MachineIdUnavailable)?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .collect::<Vec<_>>();
+    interfaces.sort();
+
+    interfaces
+        .into_iter()
+        .filter_map(|interface| std::fs::read_to_string(interface.join("address")).ok())
+        .filter_map(|address| parse_mac(&address))
+        .find(|mac| *mac != 0)
+        .map(|mac| (mac & config.machine_id_mask) as u32)
+        .ok_or(SnowflakeGeneratorError::MachineIdUnavailable)
+}
+
+/// Parse a MAC address written as six colon-separated hex octets.
+#[cfg(all(feature = "std", target_os = "linux"))]
+fn parse_mac(address: &str) -> Option<u64> {
+    let octets = address.trim().split(':').collect::<Vec<_>>();
+    if octets.len() != 6 {
+        return None;
+    }
+
+    octets.into_iter().try_fold(0, |mac, octet| {
+        if octet.len() != 2 || !octet.bytes().all(|digit| digit.is_ascii_hexdigit()) {
+            return None;
+        }
+        let octet = u8::from_str_radix(octet, 16).ok()?;
+        Some(mac << 8 | octet as u64)
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const POD_UID: &str = "0d7d6c2a-6f4b-4e0a-9a55-3c2b1f0e8d7a";
+
+    #[test]
+    fn test_machine_id_from_uid() {
+        let config = SnowflakeConfig::default();
+        let machine_id = machine_id_from_uid(POD_UID, &config);
+        assert!(machine_id as u64 <= config.machine_id_mask);
+        assert_eq!(machine_id, machine_id_from_uid(POD_UID, &config));
+        assert_ne!(
+            machine_id,
+            machine_id_from_uid("6b1c4f0e-2d3a-4c5b-8e9f-0a1b2c3d4e5f", &config)
+        );
+    }
+
+    #[test]
+    fn test_machine_id_from_uid_is_stable() {
+        // FNV-1a test vector, masked to the default 10 bits
+        let config = SnowflakeConfig::default();
+        assert_eq!(
+            machine_id_from_uid("a", &config),
+            0xAF63_DC4C_8601_EC8C_u64 as u32 & 0x3FF
+        );
+    }
+
+    #[cfg(all(feature = "std", target_os = "linux"))]
+    #[test]
+    fn test_machine_id_from_hostname() {
+        let config = SnowflakeConfig::default();
+        let hostname = std::fs::read_to_string("/proc/sys/kernel/hostname")
+            .or_else(|_| std::fs::read_to_string("/etc/hostname"));
+        match hostname {
+            Ok(hostname) => assert_eq!(
+                machine_id_from_hostname(&config).unwrap(),
+                machine_id_from_uid(hostname.trim(), &config)
+            ),
+            Err(_) => assert!(machine_id_from_hostname(&config).is_err()),
+        }
+    }
+
+    #[cfg(all(feature = "std", target_os = "linux"))]
+    #[test]
+    fn test_parse_mac() {
+        assert_eq!(parse_mac("02:fc:00:00:01:2A\n"), Some(0x02fc_0000_012a));
+        assert_eq!(parse_mac("00:00:00:00:00:00"), Some(0));
+        for address in [
+            "",
+            "02:fc:00:00:01",
+            "02:fc:00:00:01:2a:00",
+            "02:fc:00:00:1:2a0",
+            "0g:fc:00:00:01:2a",
+            "+1:fc:00:00:01:2a",
+        ] {
+            assert_eq!(parse_mac(address), None);
+        }
+    }
+}
@@ -0,0 +1,181 @@
+//! Decoding snowflakes across an epoch migration.
+
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+
+use crate::{SnowflakeConfig, SnowflakeGeneratorError};
+
+/// Move a snowflake from one epoch to another.
+///
+/// The timestamp is decoded with `old_epoch` and re-encoded relative to
+/// `new_epoch`, leaving every other field untouched, so the rebased snowflake
+/// decodes to the same point in time under the new epoch. Returns
+/// [SnowflakeGeneratorError::TimestampOverflow] if the timestamp doesn't fit the
+/// new epoch, either because it comes before it or because it is too far past it.
+///
+/// Rebased snowflakes only compare meaningfully with other snowflakes of the same
+/// epoch. A snowflake that wasn't rebased may sort before or after a rebased one
+/// regardless of when the two were generated.
+///
+/// ```rust
+/// use frostbit::{rebase_snowflake, SnowflakeConfig};
+///
+/// let config = SnowflakeConfig::default();
+/// let snowflake = 5_000 << 22 | 0x10 << 12;
+/// let rebased = rebase_snowflake(snowflake, 1_000, 4_000, &config).unwrap();
+/// assert_eq!(rebased, 2_000 << 22 | 0x10 << 12);
+/// ```
+pub fn rebase_snowflake(
+    snowflake: u64,
+    old_epoch: u64,
+    new_epoch: u64,
+    config: &SnowflakeConfig,
+) -> Result<u64, SnowflakeGeneratorError> {
+    let timestamp = config
+        .timestamp_of(snowflake)
+        .checked_add(old_epoch)
+        .and_then(|timestamp| timestamp.checked_sub(new_epoch))
+        .filter(|timestamp| *timestamp < config.timestamp_max)
+        .ok_or(SnowflakeGeneratorError::TimestampOverflow)?;
+
+    let shift = config.timestamp_shift();
+    Ok(snowflake & !(config.timestamp_mask << shift) | timestamp << shift)
+}
+
+/// Decode snowflakes generated under several epochs to absolute timestamps.
+///
+/// When a service changes its epoch, snowflakes from before and after the change
+/// coexist, and decoding an old one with the new epoch gives the wrong time. Each
+/// epoch is registered together with the first snowflake generated under it, and
+/// snowflakes are decoded with the epoch of the range they fall into. This relies
+/// on the ranges not overlapping, i.e. every snowflake generated under the new
+/// epoch must be numerically above the cutover.
+///
+/// ```rust
+/// use frostbit::{MultiEpochDecoder, SnowflakeConfig};
+///
+/// let decoder = MultiEpochDecoder::new(SnowflakeConfig::default())
+///     .with_epoch(1_000, 0)
+///     .with_epoch(2_000, 1 << 40);
+/// assert_eq!(decoder.timestamp_ms(5 << 22), Some(1_005));
+/// ```
+#[cfg(feature = "alloc")]
+#[derive(Debug, Clone)]
+pub struct MultiEpochDecoder {
+    config: SnowflakeConfig,
+    boundaries: Vec<(u64, u64)>,
+}
+
+#[cfg(feature = "alloc")]
+impl MultiEpochDecoder {
+    /// Create a new MultiEpochDecoder without any epochs.
+    pub fn new(config: SnowflakeConfig) -> Self {
+        Self {
+            config,
+            boundaries: Vec::new(),
+        }
+    }
+
+    /// Register an epoch that was used for every snowflake from `cutover_id` onwards.
+    ///
+    /// The epochs can be registered in any order. The range for an epoch ends where
+    /// the next cutover begins.
+    pub fn with_epoch(mut self, epoch: u64, cutover_id: u64) -> Self {
+        let index = self.boundaries.partition_point(|(id, _)| *id <= cutover_id);
+        self.boundaries.insert(index, (cutover_id, epoch));
+        self
+    }
+
+    /// The epoch a snowflake was generated under.
+    ///
+    /// Returns `None` if the snowflake comes before the first cutover.
+    pub fn epoch_of(&self, snowflake: u64) -> Option<u64> {
+        let index = self.boundaries.partition_point(|(id, _)| *id <= snowflake);
+        index.checked_sub(1).map(|index| self.boundaries[index].1)
+    }
+
+    /// Decode the absolute timestamp of a snowflake, using the epoch of its range.
+    ///
+    /// Returns `None` if the snowflake comes before the first cutover.
+    pub fn timestamp_ms(&self, snowflake: u64) -> Option<u64> {
+        self.epoch_of(snowflake)
+            .map(|epoch| self.config.timestamp_of(snowflake) + epoch)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const OLD_EPOCH: u64 = 1_288_834_974_657;
+    const NEW_EPOCH: u64 = 1_420_070_400_000;
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn test_multi_epoch_decode() {
+        let config = SnowflakeConfig::default();
+        let cutover = config.pack(0x1_0000_0000, 0, 0);
+        let decoder = MultiEpochDecoder::new(config)
+            .with_epoch(NEW_EPOCH, cutover)
+            .with_epoch(OLD_EPOCH, 1);
+
+        let old_id = config.pack(0x1234, 0x10, 0);
+        let new_id = config.pack(0x1_0000_1234, 0x10, 0);
+        assert_eq!(decoder.timestamp_ms(old_id), Some(OLD_EPOCH + 0x1234));
+        assert_eq!(
+            decoder.timestamp_ms(cutover),
+            Some(NEW_EPOCH + 0x1_0000_0000)
+        );
+        assert_eq!(
+            decoder.timestamp_ms(new_id),
+            Some(NEW_EPOCH + 0x1_0000_1234)
+        );
+        assert_eq!(decoder.epoch_of(cutover - 1), Some(OLD_EPOCH));
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn test_multi_epoch_before_first_cutover() {
+        let decoder = MultiEpochDecoder::new(SnowflakeConfig::default()).with_epoch(OLD_EPOCH, 10);
+        assert_eq!(decoder.timestamp_ms(9), None);
+        assert_eq!(
+            MultiEpochDecoder::new(SnowflakeConfig::default()).epoch_of(9),
+            None
+        );
+    }
+
+    #[test]
+    fn test_rebase_snowflake() {
+        let config = SnowflakeConfig::default().with_tombstone_bit(true).unwrap();
+        let snowflake = crate::mark_deleted(config.pack(0x1234, 0x10, 7), &config);
+
+        let rebased = rebase_snowflake(snowflake, OLD_EPOCH, OLD_EPOCH - 0x1000, &config).unwrap();
+        assert_eq!(
+            config.timestamp_of(rebased) + OLD_EPOCH - 0x1000,
+            OLD_EPOCH + 0x1234
+        );
+        assert_eq!(config.machine_id_of(rebased), 0x10);
+        assert_eq!(config.sequence_of(rebased), 7);
+        assert!(crate::is_deleted(rebased, &config));
+        assert_eq!(
+            rebase_snowflake(rebased, OLD_EPOCH - 0x1000, OLD_EPOCH, &config).unwrap(),
+            snowflake
+        );
+    }
+
+    #[test]
+    fn test_rebase_snowflake_out_of_range() {
+        let config = SnowflakeConfig::default();
+        let snowflake = config.pack(0x1234, 0x10, 0);
+        assert!(matches!(
+            rebase_snowflake(snowflake, OLD_EPOCH, NEW_EPOCH, &config),
+            Err(SnowflakeGeneratorError::TimestampOverflow)
+        ));
+
+        let late = config.pack(config.timestamp_max() - 1, 0x10, 0);
+        assert!(matches!(
+            rebase_snowflake(late, NEW_EPOCH, OLD_EPOCH, &config),
+            Err(SnowflakeGeneratorError::TimestampOverflow)
+        ));
+    }
+}
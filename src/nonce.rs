@@ -0,0 +1,101 @@
+//! Snowflakes with a random nonce in their lowest bits.
+
+use crate::{
+    build_mask, SnowflakeConfig, SnowflakeGenerator, SnowflakeGeneratorError, TimestampSource,
+};
+
+/// A generator that fills the nonce bits of every snowflake from an RNG.
+///
+/// The config must reserve nonce bits with [SnowflakeConfig::with_nonce_bits].
+/// The RNG is injected as a function returning random `u64`s, of which only the
+/// low nonce bits are used. For unguessable snowflakes it must be a
+/// cryptographically secure RNG, e.g. one backed by the OS, while tests can pass
+/// in a fixed or seeded one. Uniqueness never depends on the nonce.
+pub struct NonceSnowflakeGenerator<T, R>
+where
+    T: TimestampSource,
+    R: Fn() -> u64,
+{
+    inner: SnowflakeGenerator<T>,
+    get_random: R,
+    nonce_mask: u64,
+    nonce_shift: u64,
+}
+
+impl<T, R> NonceSnowflakeGenerator<T, R>
+where
+    T: TimestampSource,
+    R: Fn() -> u64,
+{
+    /// Create a new NonceSnowflakeGenerator with a custom configuration.
+    ///
+    /// Returns [SnowflakeGeneratorError::InvalidBitConfig] if the config has no
+    /// nonce bits.
+    pub fn new_with_config(
+        machine_id: u32,
+        epoch: u64,
+        get_timestamp: T,
+        config: SnowflakeConfig,
+        get_random: R,
+    ) -> Result<Self, SnowflakeGeneratorError> {
+        if config.nonce_bits == 0 {
+            return Err(SnowflakeGeneratorError::InvalidBitConfig);
+        }
+
+        let inner = SnowflakeGenerator::new_with_config(machine_id, epoch, get_timestamp, config)?;
+        Ok(Self {
+            inner,
+            get_random,
+            nonce_mask: build_mask(config.nonce_bits),
+            nonce_shift: config.nonce_shift(),
+        })
+    }
+
+    /// Generate a new snowflake with a fresh nonce.
+    pub fn generate(&self) -> Result<u64, SnowflakeGeneratorError> {
+        let snowflake = self.inner.generate()?;
+        Ok(snowflake | ((self.get_random)() & self.nonce_mask) << self.nonce_shift)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_nonce_generation() {
+        let config = SnowflakeConfig::new(41, 10, 8)
+            .unwrap()
+            .with_nonce_bits(4)
+            .unwrap();
+        let generator =
+            NonceSnowflakeGenerator::new_with_config(0x10, 0, || Ok(0x1234), config, || 0xABCD)
+                .unwrap();
+
+        let snowflake = generator.generate().unwrap();
+        assert_eq!(snowflake, 0x48D01000D);
+        assert_eq!(config.nonce_of(snowflake), 0xD);
+        assert_eq!(config.timestamp_of(snowflake), 0x1234);
+        assert_eq!(config.machine_id_of(snowflake), 0x10);
+        assert_eq!(config.sequence_of(generator.generate().unwrap()), 1);
+    }
+
+    #[test]
+    fn test_nonce_bits_in_bit_sum() {
+        let config = SnowflakeConfig::default().with_nonce_bits(2);
+        assert!(matches!(
+            config,
+            Err(SnowflakeGeneratorError::InvalidBitConfig)
+        ));
+    }
+
+    #[test]
+    fn test_nonce_bits_required() {
+        let config = SnowflakeConfig::default();
+        let result = NonceSnowflakeGenerator::new_with_config(0x10, 0, || Ok(0), config, || 0);
+        assert!(matches!(
+            result,
+            Err(SnowflakeGeneratorError::InvalidBitConfig)
+        ));
+    }
+}
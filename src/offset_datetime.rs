@@ -0,0 +1,58 @@
+//! Integration with `time` for turning snowflake timestamps into dates.
+
+use time::{OffsetDateTime, PrimitiveDateTime};
+
+use crate::{SequenceAllocator, SnowflakeGenerator, TimestampSource};
+
+impl<T: TimestampSource, S: SequenceAllocator> SnowflakeGenerator<T, S> {
+    /// The UTC date at which a snowflake was generated.
+    ///
+    /// Like [SnowflakeGenerator::decode_timestamp], the timestamp field is taken from
+    /// the bits of the config, the epoch is added back, and the result converted from
+    /// the [crate::TimeUnit] of the config, assuming the timestamp function counts
+    /// from the UNIX epoch. Dates past what `time` can represent are clamped to
+    /// [PrimitiveDateTime::MAX].
+    pub fn decode_offset_datetime(&self, snowflake: u64) -> OffsetDateTime {
+        time::Duration::try_from(self.decode_timestamp(snowflake))
+            .ok()
+            .and_then(|since_unix_epoch| OffsetDateTime::UNIX_EPOCH.checked_add(since_unix_epoch))
+            .unwrap_or(PrimitiveDateTime::MAX.assume_utc())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::SnowflakeConfig;
+
+    const TWITTER_EPOCH: u64 = 1_288_834_974_657;
+
+    #[test]
+    fn test_decode_offset_datetime() {
+        let config = SnowflakeConfig::new(40, 10, 13).unwrap();
+        let generator = SnowflakeGenerator::new_with_config(
+            0x10,
+            TWITTER_EPOCH,
+            || Ok(1_700_000_000_123),
+            config,
+        )
+        .unwrap();
+
+        let snowflake = generator.generate().unwrap();
+        let expected =
+            OffsetDateTime::from_unix_timestamp_nanos(1_700_000_000_123_000_000).unwrap();
+        assert_eq!(generator.decode_offset_datetime(snowflake), expected);
+    }
+
+    #[test]
+    fn test_decode_offset_datetime_clamped() {
+        let config = SnowflakeConfig::new(60, 2, 2).unwrap();
+        let generator = SnowflakeGenerator::new_with_config(0, 0, || Ok(0), config).unwrap();
+
+        let snowflake = config.pack(config.timestamp_max - 1, 0, 0);
+        assert_eq!(
+            generator.decode_offset_datetime(snowflake),
+            PrimitiveDateTime::MAX.assume_utc()
+        );
+    }
+}
@@ -0,0 +1,108 @@
+//! OpenTelemetry instrumentation for snowflake generation.
+
+use opentelemetry::trace::{Span, Status, Tracer};
+use opentelemetry::KeyValue;
+
+use crate::{SequenceAllocator, SnowflakeGenerator, SnowflakeGeneratorError, TimestampSource};
+
+/// A wrapper around [SnowflakeGenerator] that creates a span for every generation.
+///
+/// Each call to [TracedSnowflakeGenerator::generate] starts a `frostbit.generate`
+/// span. On success the span records the snowflake in the `snowflake.id`
+/// attribute, on failure the span status is set to an error, and a sequence
+/// overflow is also recorded as a `sequence_overflow` event.
+pub struct TracedSnowflakeGenerator<T, S, Tr>
+where
+    T: TimestampSource,
+    S: SequenceAllocator,
+    Tr: Tracer,
+{
+    inner: SnowflakeGenerator<T, S>,
+    tracer: Tr,
+}
+
+impl<T, S, Tr> TracedSnowflakeGenerator<T, S, Tr>
+where
+    T: TimestampSource,
+    S: SequenceAllocator,
+    Tr: Tracer,
+{
+    /// Wrap a generator, reporting spans to the given tracer.
+    pub fn new(inner: SnowflakeGenerator<T, S>, tracer: Tr) -> Self {
+        Self { inner, tracer }
+    }
+
+    /// Generate a new snowflake inside a `frostbit.generate` span.
+    pub fn generate(&self) -> Result<u64, SnowflakeGeneratorError> {
+        let mut span = self.tracer.start("frostbit.generate");
+        let result = self.inner.generate();
+
+        match &result {
+            Ok(snowflake) => span.set_attribute(KeyValue::new("snowflake.id", *snowflake as i64)),
+            Err(error) => {
+                if matches!(error, SnowflakeGeneratorError::SequenceOverflow) {
+                    span.add_event("sequence_overflow", vec![]);
+                }
+                span.set_status(Status::error(format!("{error:?}")));
+            }
+        }
+        span.end();
+
+        result
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use opentelemetry::trace::TracerProvider;
+    use opentelemetry::Value;
+    use opentelemetry_sdk::trace::{InMemorySpanExporter, SdkTracerProvider};
+
+    use super::*;
+
+    #[test]
+    fn test_generate_span() {
+        let exporter = InMemorySpanExporter::default();
+        let provider = SdkTracerProvider::builder()
+            .with_simple_exporter(exporter.clone())
+            .build();
+
+        let inner = SnowflakeGenerator::new(0x10, 0, || Ok(0x1234)).unwrap();
+        let generator = TracedSnowflakeGenerator::new(inner, provider.tracer("frostbit"));
+        let snowflake = generator.generate().unwrap();
+
+        let spans = exporter.get_finished_spans().unwrap();
+        assert_eq!(spans.len(), 1);
+        assert_eq!(spans[0].name, "frostbit.generate");
+        assert_eq!(
+            spans[0].attributes,
+            vec![KeyValue::new("snowflake.id", Value::I64(snowflake as i64))]
+        );
+    }
+
+    #[test]
+    fn test_overflow_span() {
+        let exporter = InMemorySpanExporter::default();
+        let provider = SdkTracerProvider::builder()
+            .with_simple_exporter(exporter.clone())
+            .build();
+
+        let inner = SnowflakeGenerator::new(0x10, 0, || Ok(0x1234)).unwrap();
+        let generator = TracedSnowflakeGenerator::new(inner, provider.tracer("frostbit"));
+        for _ in 0..4096 {
+            generator.generate().unwrap();
+        }
+        exporter.reset();
+
+        let result = generator.generate();
+        assert!(matches!(
+            result,
+            Err(SnowflakeGeneratorError::SequenceOverflow)
+        ));
+
+        let spans = exporter.get_finished_spans().unwrap();
+        assert_eq!(spans.len(), 1);
+        assert_eq!(spans[0].events.events[0].name, "sequence_overflow");
+        assert!(matches!(spans[0].status, Status::Error { .. }));
+    }
+}
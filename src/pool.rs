@@ -0,0 +1,199 @@
+//! Machine IDs leased from a pool shared by the generators of a process.
+
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use crate::{SnowflakeConfig, SnowflakeGenerator, SnowflakeGeneratorError, TimestampSource};
+
+/// A bounded pool of the machine IDs of a config, handed out as leases.
+///
+/// Meant for processes that start and stop many short-lived generators, where
+/// every generator needs a machine ID of its own while it runs. A lease returns its
+/// machine ID to the pool when it is dropped, and IDs that were never leased are
+/// handed out before returned ones, which are handed out in the order they came
+/// back. That keeps a returned ID unused for as long as possible, because a
+/// generator that takes it over within the same millisecond as the last snowflake
+/// of its previous holder can repeat that snowflake. The pool only coordinates
+/// within the process, other processes must use machine IDs of their own.
+///
+/// Cloning the pool shares it.
+#[derive(Clone)]
+pub struct MachineIdPool {
+    state: Arc<Mutex<PoolState>>,
+}
+
+struct PoolState {
+    next_unused: u64,
+    machine_id_max: u64,
+    returned: VecDeque<u32>,
+}
+
+impl MachineIdPool {
+    /// Create a pool of every machine ID that fits in the config.
+    pub fn new(config: &SnowflakeConfig) -> Self {
+        Self {
+            state: Arc::new(Mutex::new(PoolState {
+                next_unused: 0,
+                machine_id_max: config.machine_id_max(),
+                returned: VecDeque::new(),
+            })),
+        }
+    }
+
+    /// Lease a machine ID, until the lease is dropped.
+    ///
+    /// Returns [SnowflakeGeneratorError::MachineIdUnavailable] if every machine ID
+    /// is leased out. Machine IDs are `u32`s, so with more than 32 machine ID bits
+    /// the pool fails with [SnowflakeGeneratorError::MachineIdOverflow] once the
+    /// IDs that fit are leased out, rather than truncating larger ones.
+    pub fn lease(&self) -> Result<LeasedMachineId, SnowflakeGeneratorError> {
+        let mut state = lock(&self.state);
+        let machine_id = if state.next_unused <= state.machine_id_max {
+            let machine_id = u32::try_from(state.next_unused)
+                .map_err(|_| SnowflakeGeneratorError::MachineIdOverflow)?;
+            state.next_unused += 1;
+            machine_id
+        } else {
+            state
+                .returned
+                .pop_front()
+                .ok_or(SnowflakeGeneratorError::MachineIdUnavailable)?
+        };
+
+        Ok(LeasedMachineId {
+            machine_id,
+            state: self.state.clone(),
+        })
+    }
+
+    /// The number of machine IDs that can still be leased.
+    pub fn available(&self) -> u64 {
+        let state = lock(&self.state);
+        state.machine_id_max + 1 - state.next_unused + state.returned.len() as u64
+    }
+}
+
+/// A machine ID leased from a [MachineIdPool], returned to it on drop.
+pub struct LeasedMachineId {
+    machine_id: u32,
+    state: Arc<Mutex<PoolState>>,
+}
+
+impl LeasedMachineId {
+    /// The leased machine ID.
+    pub fn machine_id(&self) -> u32 {
+        self.machine_id
+    }
+}
+
+impl Drop for LeasedMachineId {
+    fn drop(&mut self) {
+        lock(&self.state).returned.push_back(self.machine_id);
+    }
+}
+
+/// A generator that holds on to the lease of its machine ID.
+///
+/// The machine ID goes back to the pool when the generator is dropped.
+pub struct LeasedGenerator<T>
+where
+    T: TimestampSource,
+{
+    inner: SnowflakeGenerator<T>,
+    lease: LeasedMachineId,
+}
+
+impl<T: TimestampSource> LeasedGenerator<T> {
+    /// Create a new generator with the machine ID of a lease.
+    ///
+    /// Otherwise this is the same as [SnowflakeGenerator::new_with_config]. The config
+    /// should be the one the pool was created with.
+    pub fn new(
+        lease: LeasedMachineId,
+        epoch: u64,
+        get_timestamp: T,
+        config: SnowflakeConfig,
+    ) -> Result<Self, SnowflakeGeneratorError> {
+        let inner =
+            SnowflakeGenerator::new_with_config(lease.machine_id(), epoch, get_timestamp, config)?;
+        Ok(Self { inner, lease })
+    }
+
+    /// Generate a new snowflake, see [SnowflakeGenerator::generate].
+    pub fn generate(&self) -> Result<u64, SnowflakeGeneratorError> {
+        self.inner.generate()
+    }
+
+    /// The leased machine ID the generator uses.
+    pub fn machine_id(&self) -> u32 {
+        self.lease.machine_id()
+    }
+
+    /// The generator itself, for everything else it offers.
+    pub fn generator(&self) -> &SnowflakeGenerator<T> {
+        &self.inner
+    }
+}
+
+fn lock(state: &Mutex<PoolState>) -> MutexGuard<'_, PoolState> {
+    // the state is never left half updated, so a poisoned lock is still usable
+    state.lock().unwrap_or_else(|error| error.into_inner())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_pool_reuses_dropped_leases() {
+        let config = SnowflakeConfig::new(42, 2, 8).unwrap();
+        let pool = MachineIdPool::new(&config);
+
+        let leases = (0..4).map(|_| pool.lease().unwrap()).collect::<Vec<_>>();
+        let machine_ids = leases
+            .iter()
+            .map(|lease| lease.machine_id())
+            .collect::<Vec<_>>();
+        assert_eq!(machine_ids, [0, 1, 2, 3]);
+        assert_eq!(pool.available(), 0);
+        assert!(matches!(
+            pool.lease(),
+            Err(SnowflakeGeneratorError::MachineIdUnavailable)
+        ));
+
+        let mut leases = leases.into_iter();
+        let (first, second) = (leases.next().unwrap(), leases.next().unwrap());
+        drop(second);
+        drop(first);
+        assert_eq!(pool.available(), 2);
+        assert_eq!(pool.lease().unwrap().machine_id(), 1);
+    }
+
+    #[test]
+    fn test_pool_past_u32_machine_ids() {
+        let config = SnowflakeConfig::new(20, 34, 8).unwrap();
+        let pool = MachineIdPool::new(&config);
+        lock(&pool.state).next_unused = u32::MAX as u64;
+
+        let lease = pool.lease().unwrap();
+        assert_eq!(lease.machine_id(), u32::MAX);
+        assert!(matches!(
+            pool.lease(),
+            Err(SnowflakeGeneratorError::MachineIdOverflow)
+        ));
+    }
+
+    #[test]
+    fn test_leased_generator() {
+        let config = SnowflakeConfig::new(42, 2, 8).unwrap();
+        let pool = MachineIdPool::new(&config);
+
+        let generator =
+            LeasedGenerator::new(pool.lease().unwrap(), 0, || Ok(0x1234), config).unwrap();
+        assert_eq!(generator.generate().unwrap(), config.pack(0x1234, 0, 0));
+        assert_eq!(pool.available(), 3);
+
+        drop(generator);
+        assert_eq!(pool.available(), 4);
+    }
+}
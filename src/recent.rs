@@ -0,0 +1,161 @@
+//! Probabilistic deduplication of recently seen snowflakes.
+//!
+//! Generated snowflakes are unique, but pipelines can still process the same one
+//! twice, e.g. when a message is redelivered. The filter here catches most such
+//! repeats using a fixed amount of memory.
+
+use crate::splitmix64;
+
+/// A bloom filter over the most recently inserted snowflakes.
+///
+/// The filter keeps two generations of bloom filters, each sized for `window`
+/// snowflakes at the given false positive rate. Once the current generation is
+/// full, the older one is dropped and a fresh one is started. The window is
+/// therefore remembered in full, and anything older than two windows is
+/// forgotten. Snowflakes inside the window are never missed, while snowflakes
+/// that were never inserted are reported as present at roughly twice the false
+/// positive rate, as both generations are checked.
+#[derive(Debug, Clone)]
+pub struct RecentIdFilter {
+    current: BloomFilter,
+    previous: BloomFilter,
+    window: usize,
+    inserted: usize,
+}
+
+impl RecentIdFilter {
+    /// Create a new RecentIdFilter remembering at least `window` snowflakes.
+    ///
+    /// Panics if the window is 0 or the false positive rate isn't between 0 and 1.
+    pub fn new(window: usize, false_positive_rate: f64) -> Self {
+        assert!(window > 0, "window must not be empty");
+        assert!(
+            false_positive_rate > 0.0 && false_positive_rate < 1.0,
+            "false positive rate must be between 0 and 1"
+        );
+
+        let filter = BloomFilter::new(window, false_positive_rate);
+        Self {
+            current: filter.clone(),
+            previous: filter,
+            window,
+            inserted: 0,
+        }
+    }
+
+    /// Record a snowflake as seen.
+    pub fn insert(&mut self, id: u64) {
+        if self.inserted == self.window {
+            core::mem::swap(&mut self.current, &mut self.previous);
+            self.current.clear();
+            self.inserted = 0;
+        }
+
+        self.current.insert(id);
+        self.inserted += 1;
+    }
+
+    /// Check whether a snowflake has probably been seen within the window.
+    ///
+    /// A `false` is always correct for snowflakes inserted within the window, a
+    /// `true` may be a false positive.
+    pub fn contains_probably(&self, id: u64) -> bool {
+        self.current.contains(id) || self.previous.contains(id)
+    }
+}
+
+#[derive(Debug, Clone)]
+struct BloomFilter {
+    bits: Vec<u64>,
+    bit_count: u64,
+    hash_count: u32,
+}
+
+impl BloomFilter {
+    fn new(capacity: usize, false_positive_rate: f64) -> Self {
+        let ln2 = core::f64::consts::LN_2;
+        let bit_count = (-(capacity as f64) * false_positive_rate.ln() / (ln2 * ln2)).ceil();
+        let bit_count = (bit_count as u64).max(64);
+        let hash_count = ((bit_count as f64 / capacity as f64) * ln2)
+            .round()
+            .max(1.0);
+
+        Self {
+            bits: vec![0; bit_count.div_ceil(64) as usize],
+            bit_count,
+            hash_count: hash_count as u32,
+        }
+    }
+
+    fn clear(&mut self) {
+        self.bits.fill(0);
+    }
+
+    fn insert(&mut self, id: u64) {
+        for bit in self.bit_indexes(id) {
+            self.bits[(bit / 64) as usize] |= 1 << (bit % 64);
+        }
+    }
+
+    fn contains(&self, id: u64) -> bool {
+        self.bit_indexes(id)
+            .all(|bit| self.bits[(bit / 64) as usize] & 1 << (bit % 64) != 0)
+    }
+
+    /// Double hashing, deriving every index from two independent hashes of the id.
+    fn bit_indexes(&self, id: u64) -> impl Iterator<Item = u64> {
+        let bit_count = self.bit_count;
+        let first = splitmix64(id);
+        let second = splitmix64(id ^ 0x9E37_79B9_7F4A_7C15) | 1;
+        (0..self.hash_count as u64)
+            .map(move |i| first.wrapping_add(i.wrapping_mul(second)) % bit_count)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::SnowflakeConfig;
+
+    fn snowflakes(range: std::ops::Range<u64>) -> impl Iterator<Item = u64> {
+        let config = SnowflakeConfig::default();
+        range.map(move |i| config.pack(0x1234 + i / 4096, 0x10, i % 4096))
+    }
+
+    #[test]
+    fn test_no_false_negatives_in_window() {
+        let mut filter = RecentIdFilter::new(10_000, 0.01);
+        for id in snowflakes(0..10_000) {
+            filter.insert(id);
+        }
+
+        assert!(snowflakes(0..10_000).all(|id| filter.contains_probably(id)));
+    }
+
+    #[test]
+    fn test_false_positive_rate() {
+        let mut filter = RecentIdFilter::new(10_000, 0.01);
+        for id in snowflakes(0..20_000) {
+            filter.insert(id);
+        }
+
+        // both generations are full, so the rate is at most twice the target
+        let false_positives = snowflakes(20_000..120_000)
+            .filter(|id| filter.contains_probably(*id))
+            .count();
+        assert!(false_positives < 2_500, "{false_positives} false positives");
+    }
+
+    #[test]
+    fn test_old_ids_forgotten() {
+        let mut filter = RecentIdFilter::new(1_000, 0.001);
+        for id in snowflakes(0..3_000) {
+            filter.insert(id);
+        }
+
+        let remembered = snowflakes(0..1_000)
+            .filter(|id| filter.contains_probably(*id))
+            .count();
+        assert!(remembered < 20, "{remembered} old IDs remembered");
+    }
+}
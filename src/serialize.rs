@@ -0,0 +1,165 @@
+//! Serde support for [SnowflakeConfig].
+//!
+//! Only the settings a config was built from are serialized, the masks and maxes
+//! derived from them are not. Deserializing rebuilds the config through the same
+//! constructors as in code, so a config that would be rejected there fails to load.
+//! Everything but the three bit counts is optional, and defaults as in
+//! [SnowflakeConfig::new].
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    AtomicOrdering, ClockBackwardsPolicy, FieldOrder, MachineIdPolicy, OverflowPolicy,
+    SnowflakeConfig, SnowflakeGeneratorError, TimeUnit,
+};
+
+#[derive(Serialize, Deserialize)]
+pub(crate) struct SnowflakeConfigRepr {
+    timestamp_bits: u64,
+    machine_id_bits: u64,
+    sequence_bits: u64,
+    #[serde(default)]
+    era_bits: u64,
+    #[serde(default)]
+    datacenter_bits: u64,
+    #[serde(default)]
+    flavor_bits: u64,
+    #[serde(default)]
+    tombstone_bit: bool,
+    #[serde(default)]
+    prefix_bits: u64,
+    #[serde(default)]
+    prefix_value: u64,
+    #[serde(default)]
+    reserved_bits: u64,
+    #[serde(default)]
+    nonce_bits: u64,
+    #[serde(default)]
+    hmac_bits: u64,
+    #[serde(default)]
+    min_timestamp_ms: u64,
+    #[serde(default)]
+    machine_id_policy: MachineIdPolicy,
+    #[serde(default)]
+    overflow_policy: OverflowPolicy,
+    #[serde(default)]
+    clock_backwards_policy: ClockBackwardsPolicy,
+    #[serde(default)]
+    atomic_ordering: AtomicOrdering,
+    #[serde(default)]
+    time_unit: TimeUnit,
+    #[serde(default)]
+    field_order: FieldOrder,
+}
+
+impl From<SnowflakeConfig> for SnowflakeConfigRepr {
+    fn from(config: SnowflakeConfig) -> Self {
+        Self {
+            timestamp_bits: config.timestamp_bits,
+            // the era is carved out of the machine ID bits
+            machine_id_bits: config.machine_id_bits + config.era_bits,
+            sequence_bits: config.sequence_bits,
+            era_bits: config.era_bits,
+            datacenter_bits: config.datacenter_bits,
+            flavor_bits: config.flavor_bits,
+            tombstone_bit: config.tombstone_bits > 0,
+            prefix_bits: config.prefix_bits,
+            prefix_value: config.prefix_value,
+            reserved_bits: config.reserved_bits,
+            nonce_bits: config.nonce_bits,
+            hmac_bits: config.hmac_bits,
+            min_timestamp_ms: config.min_timestamp_ms,
+            machine_id_policy: config.machine_id_policy,
+            overflow_policy: config.overflow_policy,
+            clock_backwards_policy: config.clock_backwards_policy,
+            atomic_ordering: config.atomic_ordering,
+            time_unit: config.time_unit,
+            field_order: config.field_order,
+        }
+    }
+}
+
+impl TryFrom<SnowflakeConfigRepr> for SnowflakeConfig {
+    type Error = SnowflakeGeneratorError;
+
+    fn try_from(repr: SnowflakeConfigRepr) -> Result<Self, Self::Error> {
+        let config = SnowflakeConfig::new(
+            repr.timestamp_bits,
+            repr.machine_id_bits,
+            repr.sequence_bits,
+        )?
+        .with_era_bits(repr.era_bits)?;
+        let worker_bits = config.machine_id_bits.saturating_sub(repr.datacenter_bits);
+
+        config
+            .with_datacenter_bits(repr.datacenter_bits, worker_bits)?
+            .with_flavor_bits(repr.flavor_bits)?
+            .with_tombstone_bit(repr.tombstone_bit)?
+            .with_prefix(repr.prefix_bits, repr.prefix_value)?
+            .with_reserved_bits(repr.reserved_bits)?
+            .with_nonce_bits(repr.nonce_bits)?
+            .with_hmac_bits(repr.hmac_bits)?
+            .with_min_timestamp_ms(repr.min_timestamp_ms)
+            .with_machine_id_policy(repr.machine_id_policy)
+            .with_overflow_policy(repr.overflow_policy)
+            .with_field_order(repr.field_order)
+            .with_clock_backwards_policy(repr.clock_backwards_policy)
+            .with_atomic_ordering(repr.atomic_ordering)
+            .with_time_unit(repr.time_unit)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_config_round_trip() {
+        let config = SnowflakeConfig::new(39, 10, 12)
+            .unwrap()
+            .with_era_bits(2)
+            .unwrap()
+            .with_datacenter_bits(3, 5)
+            .unwrap()
+            .with_prefix(2, 0b10)
+            .unwrap()
+            .with_reserved_bits(1)
+            .unwrap()
+            .with_machine_id_policy(MachineIdPolicy::Mask)
+            .with_clock_backwards_policy(ClockBackwardsPolicy::Reject { tolerance_ms: 5 })
+            .with_atomic_ordering(AtomicOrdering::Relaxed)
+            .with_time_unit(TimeUnit::Micros)
+            .unwrap();
+
+        let json = serde_json::to_string(&config).unwrap();
+        let decoded: SnowflakeConfig = serde_json::from_str(&json).unwrap();
+        assert_eq!(decoded, config);
+        assert_eq!(decoded.layout(), config.layout());
+        assert_eq!(decoded.timestamp_mask, config.timestamp_mask);
+        assert_eq!(decoded.machine_id_mask, config.machine_id_mask);
+        assert_eq!(decoded.machine_id_policy, MachineIdPolicy::Mask);
+        assert_eq!(decoded.datacenter_bits, 3);
+        assert_eq!(decoded.reserved_bits, 1);
+        assert_eq!(
+            decoded.clock_backwards_policy,
+            config.clock_backwards_policy
+        );
+        assert_eq!(decoded.atomic_ordering, AtomicOrdering::Relaxed);
+        assert_eq!(decoded.time_unit(), TimeUnit::Micros);
+        assert_eq!(decoded.pack(0x1234, 0x10, 5), config.pack(0x1234, 0x10, 5));
+    }
+
+    #[test]
+    fn test_config_from_bit_counts() {
+        let json = r#"{"timestamp_bits": 41, "machine_id_bits": 10, "sequence_bits": 12}"#;
+        let config: SnowflakeConfig = serde_json::from_str(json).unwrap();
+        assert_eq!(config.layout(), SnowflakeConfig::default().layout());
+        assert_eq!(config.sequence_max, 4095);
+    }
+
+    #[test]
+    fn test_invalid_config_fails_to_load() {
+        let json = r#"{"timestamp_bits": 41, "machine_id_bits": 20, "sequence_bits": 12}"#;
+        assert!(serde_json::from_str::<SnowflakeConfig>(json).is_err());
+    }
+}
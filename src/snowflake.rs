@@ -0,0 +1,66 @@
+//! A typed wrapper for snowflakes.
+
+use crate::SnowflakeConfig;
+
+/// A snowflake, kept apart from other `u64`s by the type system.
+///
+/// Returned by [crate::SnowflakeGenerator::generate_typed]. The fields are decoded
+/// with the config the snowflake was generated with, which isn't stored alongside
+/// it. Converting from and to `u64` is free.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Snowflake(u64);
+
+impl Snowflake {
+    /// The snowflake as a plain `u64`.
+    pub fn raw(&self) -> u64 {
+        self.0
+    }
+
+    /// The epoch-relative timestamp of the snowflake.
+    pub fn timestamp(&self, config: &SnowflakeConfig) -> u64 {
+        config.timestamp_of(self.0)
+    }
+
+    /// The machine ID of the snowflake.
+    pub fn machine_id(&self, config: &SnowflakeConfig) -> u64 {
+        config.machine_id_of(self.0)
+    }
+
+    /// The sequence ID of the snowflake.
+    pub fn sequence(&self, config: &SnowflakeConfig) -> u64 {
+        config.sequence_of(self.0)
+    }
+}
+
+impl From<u64> for Snowflake {
+    fn from(snowflake: u64) -> Self {
+        Self(snowflake)
+    }
+}
+
+impl From<Snowflake> for u64 {
+    fn from(snowflake: Snowflake) -> Self {
+        snowflake.0
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::SnowflakeGenerator;
+
+    #[test]
+    fn test_typed_snowflake() {
+        let config = SnowflakeConfig::new(42, 5, 8).unwrap();
+        let generator =
+            SnowflakeGenerator::new_with_config(0x1A, 0, || Ok(0x1234), config).unwrap();
+        generator.generate().unwrap();
+
+        let snowflake = generator.generate_typed().unwrap();
+        assert_eq!(snowflake.timestamp(&config), 0x1234);
+        assert_eq!(snowflake.machine_id(&config), 0x1A);
+        assert_eq!(snowflake.sequence(&config), 1);
+        assert_eq!(Snowflake::from(snowflake.raw()), snowflake);
+        assert_eq!(u64::from(snowflake), config.pack(0x1234, 0x1A, 1));
+    }
+}
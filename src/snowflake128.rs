@@ -0,0 +1,297 @@
+//! 128-bit snowflakes, for layouts that don't fit in a `u64`.
+//!
+//! There is no stable `AtomicU128`, so the generator keeps the timestamp and
+//! sequence packed in the same [TimestampSequenceGenerator] used for 64-bit
+//! snowflakes, and only widens the output. The sequence and its overflow bit
+//! take up the low bits of the atomic, which leaves `63 - sequence_bits` bits
+//! for the stored timestamp. For a 16-bit sequence that is 47 bits, or roughly
+//! 4000 years of milliseconds past the epoch.
+
+use crate::timestamp_sequence::TimestampSequenceGenerator;
+use crate::{build_mask, SnowflakeConfig, SnowflakeGeneratorError, TimestampSource};
+
+const FLAKE_TIMESTAMP_BITS: u64 = 64;
+const FLAKE_WORKER_ID_BITS: u64 = 48;
+const FLAKE_SEQUENCE_BITS: u64 = 16;
+
+/// Configuration for a 128-bit snowflake generator.
+///
+/// The SnowflakeConfig128 struct is the 128-bit counterpart of [SnowflakeConfig].
+/// The bit counts may sum up to 128, but the timestamp and machine ID are still
+/// limited to 64 bits each.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SnowflakeConfig128 {
+    machine_id_bits: u64,
+    sequence_bits: u64,
+    timestamp_mask: u128,
+    machine_id_mask: u128,
+    sequence_mask: u128,
+    state_config: SnowflakeConfig,
+}
+
+impl SnowflakeConfig128 {
+    /// Create a new [SnowflakeConfig128] with the given number of bits for each field.
+    pub fn new(
+        timestamp_bits: u64,
+        machine_id_bits: u64,
+        sequence_bits: u64,
+    ) -> Result<Self, SnowflakeGeneratorError> {
+        let bit_sum = timestamp_bits + machine_id_bits + sequence_bits;
+        if bit_sum > 128 || timestamp_bits > 64 || machine_id_bits > 64 || sequence_bits > 62 {
+            return Err(SnowflakeGeneratorError::InvalidBitConfig);
+        }
+
+        if machine_id_bits == 0 || sequence_bits == 0 || timestamp_bits == 0 {
+            return Err(SnowflakeGeneratorError::InvalidBitConfig);
+        }
+
+        // The atomic state only ever holds the timestamp and sequence, so a single
+        // machine ID bit is enough to hold the sequence overflow bit.
+        let state_timestamp_bits = timestamp_bits.min(63 - sequence_bits);
+        let state_config = SnowflakeConfig::new(state_timestamp_bits, 1, sequence_bits)?;
+
+        Ok(Self {
+            machine_id_bits,
+            sequence_bits,
+            timestamp_mask: build_mask(timestamp_bits) as u128,
+            machine_id_mask: build_mask(machine_id_bits) as u128,
+            sequence_mask: build_mask(sequence_bits) as u128,
+            state_config,
+        })
+    }
+
+    /// Create a config for Boundary's [Flake](https://github.com/boundary/flake) format.
+    ///
+    /// Flake IDs use a 64-bit millisecond timestamp since the UNIX epoch, a 48-bit
+    /// worker ID (usually a MAC address), and a 16-bit sequence.
+    pub fn flake() -> Self {
+        Self::new(
+            FLAKE_TIMESTAMP_BITS,
+            FLAKE_WORKER_ID_BITS,
+            FLAKE_SEQUENCE_BITS,
+        )
+        .expect("Flake values incorrect")
+    }
+
+    /// Reject timestamps below the given epoch-relative floor.
+    ///
+    /// Generation fails with [SnowflakeGeneratorError::TimestampBelowFloor], as with
+    /// [SnowflakeConfig::with_min_timestamp_ms] for 64-bit snowflakes.
+    pub fn with_min_timestamp_ms(mut self, min_timestamp_ms: u64) -> Self {
+        self.state_config = self.state_config.with_min_timestamp_ms(min_timestamp_ms);
+        self
+    }
+
+    /// Split a 128-bit snowflake back into its timestamp, machine ID, and sequence.
+    pub fn decode(&self, snowflake: u128) -> DecodedSnowflake128 {
+        DecodedSnowflake128 {
+            timestamp: ((snowflake >> self.timestamp_shift()) & self.timestamp_mask) as u64,
+            machine_id: ((snowflake >> self.sequence_bits) & self.machine_id_mask) as u64,
+            sequence: (snowflake & self.sequence_mask) as u64,
+        }
+    }
+
+    fn timestamp_shift(&self) -> u64 {
+        self.machine_id_bits + self.sequence_bits
+    }
+
+    fn pack(&self, timestamp: u64, machine_id: u64, sequence: u64) -> u128 {
+        let timestamp_bits = timestamp as u128 & self.timestamp_mask;
+        let machine_id_bits = machine_id as u128 & self.machine_id_mask;
+        let sequence_bits = sequence as u128 & self.sequence_mask;
+
+        timestamp_bits << self.timestamp_shift()
+            | machine_id_bits << self.sequence_bits
+            | sequence_bits
+    }
+}
+
+/// The fields of a 128-bit snowflake, as returned by [SnowflakeConfig128::decode].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct DecodedSnowflake128 {
+    pub timestamp: u64,
+    pub machine_id: u64,
+    pub sequence: u64,
+}
+
+/// A generator for creating unique 128-bit snowflake IDs.
+///
+/// Works the same way as [crate::SnowflakeGenerator], but with a wider machine ID
+/// and output.
+pub struct SnowflakeGenerator128<T>
+where
+    T: TimestampSource,
+{
+    machine_id: u64,
+    ts_gen: TimestampSequenceGenerator,
+    epoch: u64,
+    get_timestamp: T,
+    config: SnowflakeConfig128,
+}
+
+impl<T: TimestampSource> SnowflakeGenerator128<T> {
+    /// Create a new SnowflakeGenerator128 producing Flake IDs.
+    ///
+    /// The worker ID is 48 bits wide, see [worker_id_from_mac] to derive it from a
+    /// MAC address. Flake timestamps are relative to the UNIX epoch, so the
+    /// timestamp function should return milliseconds since the UNIX epoch.
+    pub fn flake(worker_id: u64, get_timestamp: T) -> Result<Self, SnowflakeGeneratorError> {
+        Self::new_with_config(worker_id, 0, get_timestamp, SnowflakeConfig128::flake())
+    }
+
+    /// Create a new SnowflakeGenerator128 with a custom configuration.
+    ///
+    /// Returns [SnowflakeGeneratorError::MachineIdOverflow] if the machine ID doesn't
+    /// fit in the machine ID bits of the config.
+    pub fn new_with_config(
+        machine_id: u64,
+        epoch: u64,
+        get_timestamp: T,
+        config: SnowflakeConfig128,
+    ) -> Result<Self, SnowflakeGeneratorError> {
+        if machine_id as u128 > config.machine_id_mask {
+            return Err(SnowflakeGeneratorError::MachineIdOverflow);
+        }
+
+        let timestamp_ms = Self::get_epoch_relative_timestamp(&get_timestamp, epoch, &config)?;
+        let ts_gen = TimestampSequenceGenerator::new(timestamp_ms, config.state_config);
+        Ok(Self {
+            machine_id,
+            ts_gen,
+            epoch,
+            get_timestamp,
+            config,
+        })
+    }
+
+    /// Generate a new 128-bit snowflake.
+    ///
+    /// This function generates a new snowflake ID. If the sequence overflows,
+    /// it will return [SnowflakeGeneratorError::SequenceOverflow].
+    pub fn generate_u128(&self) -> Result<u128, SnowflakeGeneratorError> {
+        let new_timestamp =
+            Self::get_epoch_relative_timestamp(&self.get_timestamp, self.epoch, &self.config)?;
+        let timestamp_sequence = self.ts_gen.increment_sequence(new_timestamp)?;
+
+        Ok(self.config.pack(
+            timestamp_sequence.timestamp,
+            self.machine_id,
+            timestamp_sequence.sequence,
+        ))
+    }
+
+    fn get_epoch_relative_timestamp(
+        get_timestamp: &T,
+        epoch: u64,
+        config: &SnowflakeConfig128,
+    ) -> Result<u64, SnowflakeGeneratorError> {
+        config
+            .state_config
+            .epoch_relative(get_timestamp.timestamp()?, epoch)
+    }
+}
+
+/// Build a 48-bit worker ID from a MAC address, for use with Flake IDs.
+pub fn worker_id_from_mac(mac: [u8; 6]) -> u64 {
+    mac.iter()
+        .fold(0u64, |worker_id, byte| worker_id << 8 | *byte as u64)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const FLAKE_TIMESTAMP: u64 = 1_388_534_400_000;
+    const FLAKE_MAC: [u8; 6] = [0x00, 0x11, 0x22, 0x33, 0x44, 0x55];
+    // Flake builds its IDs as the Erlang binary
+    // `<<Time:64/integer, WorkerId:48/integer, Sequence:16/integer>>`, i.e. the
+    // big-endian bytes of each field in turn, here for 2014-01-01T00:00:00Z, the
+    // MAC above, and sequence 7.
+    const FLAKE_ID: u128 = u128::from_be_bytes([
+        0x00, 0x00, 0x01, 0x43, 0x4B, 0x19, 0x84, 0x00, // time
+        0x00, 0x11, 0x22, 0x33, 0x44, 0x55, // worker ID
+        0x00, 0x07, // sequence
+    ]);
+
+    #[test]
+    fn test_flake_decode() {
+        let decoded = SnowflakeConfig128::flake().decode(FLAKE_ID);
+        assert_eq!(
+            decoded,
+            DecodedSnowflake128 {
+                timestamp: FLAKE_TIMESTAMP,
+                machine_id: 0x0011_2233_4455,
+                sequence: 7,
+            }
+        );
+    }
+
+    #[test]
+    fn test_flake_generation() {
+        let worker_id = worker_id_from_mac(FLAKE_MAC);
+        let generator = SnowflakeGenerator128::flake(worker_id, || Ok(FLAKE_TIMESTAMP)).unwrap();
+
+        let mut snowflake = 0;
+        for _ in 0..8 {
+            snowflake = generator.generate_u128().unwrap();
+        }
+        assert_eq!(snowflake, FLAKE_ID);
+    }
+
+    #[test]
+    fn test_custom_layout() {
+        let config = SnowflakeConfig128::new(48, 64, 16).unwrap();
+        let machine_id = 0xDEAD_BEEF_CAFE_F00D;
+        let generator =
+            SnowflakeGenerator128::new_with_config(machine_id, 0x1000, || Ok(0x1234), config)
+                .unwrap();
+
+        for sequence in 0..2 {
+            let decoded = config.decode(generator.generate_u128().unwrap());
+            assert_eq!(
+                decoded,
+                DecodedSnowflake128 {
+                    timestamp: 0x234,
+                    machine_id,
+                    sequence,
+                }
+            );
+        }
+    }
+
+    #[test]
+    fn test_timestamp_floor() {
+        let config = SnowflakeConfig128::flake().with_min_timestamp_ms(FLAKE_TIMESTAMP);
+        assert!(
+            SnowflakeGenerator128::new_with_config(0, 0, || Ok(FLAKE_TIMESTAMP), config).is_ok()
+        );
+        assert!(matches!(
+            SnowflakeGenerator128::new_with_config(0, 0, || Ok(FLAKE_TIMESTAMP - 1), config),
+            Err(SnowflakeGeneratorError::TimestampBelowFloor)
+        ));
+    }
+
+    #[test]
+    fn test_machine_id_boundary() {
+        let config = SnowflakeConfig128::new(64, 20, 16).unwrap();
+        assert!(SnowflakeGenerator128::new_with_config(0xFFFFF, 0, || Ok(0), config).is_ok());
+        assert!(matches!(
+            SnowflakeGenerator128::new_with_config(0x100000, 0, || Ok(0), config),
+            Err(SnowflakeGeneratorError::MachineIdOverflow)
+        ));
+    }
+
+    #[test]
+    fn test_invalid_config_too_many_bits() {
+        let config = SnowflakeConfig128::new(64, 60, 16);
+        assert!(matches!(
+            config,
+            Err(SnowflakeGeneratorError::InvalidBitConfig)
+        ));
+        let config = SnowflakeConfig128::new(32, 65, 16);
+        assert!(matches!(
+            config,
+            Err(SnowflakeGeneratorError::InvalidBitConfig)
+        ));
+    }
+}
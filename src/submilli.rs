@@ -0,0 +1,176 @@
+//! Snowflakes that sort by the time within the millisecond they were created in.
+//!
+//! The high bits of the sequence are given over to the fraction of the millisecond
+//! the snowflake was generated at, and the low bits to the usual counter. Within a
+//! millisecond snowflakes then sort by their actual sub-millisecond time first, and
+//! by the counter second. The timestamp field is untouched, so these snowflakes
+//! decode like any other with the same config.
+
+use crate::timestamp_sequence::TimestampSequenceGenerator;
+use crate::{SnowflakeConfig, SnowflakeGeneratorError, TimestampSource};
+
+const MICROS_PER_MILLI: u64 = 1000;
+
+/// A generator that splits the sequence into `[submilli | counter]`.
+///
+/// The timestamp function returns microseconds rather than milliseconds, but the
+/// epoch is still in milliseconds. The microsecond within the millisecond is scaled
+/// down to fit in the sub-millisecond bits, so with fewer than 10 bits several
+/// microseconds share a value. Uniqueness comes from the counter alone, which
+/// counts up across the whole millisecond, so only `2^(sequence_bits -
+/// submilli_bits)` snowflakes can be generated per millisecond.
+pub struct SubMillisecondGenerator<T>
+where
+    T: TimestampSource,
+{
+    machine_id: u32,
+    ts_gen: TimestampSequenceGenerator,
+    epoch: u64,
+    get_timestamp_us: T,
+    config: SnowflakeConfig,
+    counter_bits: u64,
+    submilli_bits: u64,
+}
+
+impl<T: TimestampSource> SubMillisecondGenerator<T> {
+    /// Create a new SubMillisecondGenerator with a custom configuration.
+    ///
+    /// The sub-millisecond bits are taken from the top of the sequence bits, and must
+    /// leave at least one bit for the counter. The machine ID is checked against the
+    /// config the same way [crate::SnowflakeGenerator::new_with_config] does.
+    pub fn new_with_config(
+        machine_id: u32,
+        epoch: u64,
+        get_timestamp_us: T,
+        config: SnowflakeConfig,
+        submilli_bits: u64,
+    ) -> Result<Self, SnowflakeGeneratorError> {
+        if submilli_bits == 0 {
+            return Err(SnowflakeGeneratorError::InvalidBitConfig);
+        }
+        config.machine_id_policy.check(machine_id, &config)?;
+
+        let counter_config = config.with_sequence_split(submilli_bits)?;
+
+        let (timestamp_ms, _) =
+            Self::get_epoch_relative_timestamp(&get_timestamp_us, epoch, &config)?;
+        let ts_gen = TimestampSequenceGenerator::new(timestamp_ms, counter_config);
+        Ok(Self {
+            machine_id,
+            ts_gen,
+            epoch,
+            get_timestamp_us,
+            config,
+            counter_bits: counter_config.sequence_bits,
+            submilli_bits,
+        })
+    }
+
+    /// Generate a new snowflake tagged with the time within the current millisecond.
+    ///
+    /// Returns [SnowflakeGeneratorError::SequenceOverflow] once the counter runs out
+    /// for the current millisecond.
+    pub fn generate(&self) -> Result<u64, SnowflakeGeneratorError> {
+        let (timestamp_ms, micros) =
+            Self::get_epoch_relative_timestamp(&self.get_timestamp_us, self.epoch, &self.config)?;
+        let timestamp_sequence = self.ts_gen.increment_sequence(timestamp_ms)?;
+
+        // The counter may have moved on to a later millisecond than the clock read,
+        // in which case the snowflake sorts at its very start.
+        let submilli = if timestamp_sequence.timestamp == timestamp_ms {
+            (micros << self.submilli_bits) / MICROS_PER_MILLI
+        } else {
+            0
+        };
+
+        Ok(self.config.pack(
+            timestamp_sequence.timestamp,
+            self.machine_id as u64,
+            submilli << self.counter_bits | timestamp_sequence.sequence,
+        ))
+    }
+
+    fn get_epoch_relative_timestamp(
+        get_timestamp_us: &T,
+        epoch: u64,
+        config: &SnowflakeConfig,
+    ) -> Result<(u64, u64), SnowflakeGeneratorError> {
+        let timestamp_us = get_timestamp_us.timestamp()?;
+        let timestamp_ms = config.epoch_relative(timestamp_us / MICROS_PER_MILLI, epoch)?;
+        Ok((timestamp_ms, timestamp_us % MICROS_PER_MILLI))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::atomic::{AtomicU64, Ordering};
+
+    use super::*;
+
+    #[test]
+    fn test_submilli_ordering() {
+        let config = SnowflakeConfig::default();
+        let clock = AtomicU64::new(5_000_000);
+        let generator = SubMillisecondGenerator::new_with_config(
+            0x10,
+            0,
+            || Ok(clock.load(Ordering::SeqCst)),
+            config,
+            4,
+        )
+        .unwrap();
+
+        let mut prev = 0;
+        for micros in [5_000_100, 5_000_100, 5_000_400, 5_000_999, 5_001_000] {
+            clock.store(micros, Ordering::SeqCst);
+            let snowflake = generator.generate().unwrap();
+            assert!(snowflake > prev);
+            assert_eq!(config.timestamp_of(snowflake), micros / 1000);
+            prev = snowflake;
+        }
+
+        clock.store(5_002_500, Ordering::SeqCst);
+        let snowflake = generator.generate().unwrap();
+        assert_eq!(config.sequence_of(snowflake), 8 << 8);
+    }
+
+    #[test]
+    fn test_submilli_counter_overflow() {
+        let config = SnowflakeConfig::default();
+        let generator =
+            SubMillisecondGenerator::new_with_config(0x10, 0, || Ok(5_000_500), config, 10)
+                .unwrap();
+        for _ in 0..4 {
+            generator.generate().unwrap();
+        }
+
+        let result = generator.generate();
+        assert!(matches!(
+            result,
+            Err(SnowflakeGeneratorError::SequenceOverflow)
+        ));
+    }
+
+    #[test]
+    fn test_submilli_bits_must_leave_counter() {
+        let config = SnowflakeConfig::default();
+        let result = SubMillisecondGenerator::new_with_config(0x10, 0, || Ok(0), config, 12);
+        assert!(matches!(
+            result,
+            Err(SnowflakeGeneratorError::InvalidBitConfig)
+        ));
+    }
+
+    #[test]
+    fn test_submilli_machine_id_overflow() {
+        let config = SnowflakeConfig::default();
+        let result = SubMillisecondGenerator::new_with_config(0x3FF, 0, || Ok(0), config, 4);
+        assert!(result.is_ok());
+
+        let result = SubMillisecondGenerator::new_with_config(0x400, 0, || Ok(0), config, 4);
+        assert!(matches!(
+            result,
+            Err(SnowflakeGeneratorError::MachineIdOverflow)
+        ));
+    }
+}
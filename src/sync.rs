@@ -2,4 +2,4 @@
 pub(crate) use loom::sync::atomic::AtomicU64;
 
 #[cfg(not(loom))]
-pub(crate) use std::sync::atomic::AtomicU64;
+pub(crate) use core::sync::atomic::AtomicU64;
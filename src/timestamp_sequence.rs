@@ -1,7 +1,10 @@
-use std::sync::atomic::Ordering;
+use core::sync::atomic::Ordering;
 
 use crate::sync::AtomicU64;
-use crate::{build_mask, SnowflakeConfig, SnowflakeGeneratorError};
+use crate::{build_mask, ClockBackwardsPolicy, SnowflakeConfig, SnowflakeGeneratorError};
+
+/// The number of bits the sequence is extended by to detect overflows.
+pub(crate) const OVERFLOW_GUARD_BITS: u64 = 1;
 
 /// Stores both a 42-bit timestamp and 12-bit sequence in a single atomic.
 ///
@@ -12,83 +15,280 @@ use crate::{build_mask, SnowflakeConfig, SnowflakeGeneratorError};
 /// Bits 13-20: unused
 /// Bits 21-62: timestamp
 ///
-/// Note that both the sequence and timestamp are 1 bit larger than needed. This allows
-/// us to easily handle and check for overflows.
-pub(crate) struct TimestampSequenceGenerator {
+/// Note that the sequence is extended by overflow guard bits. This allows us to easily
+/// handle and check for overflows. The guard bits take up the bits directly above the
+/// sequence, which [SnowflakeConfig] makes sure are below the timestamp. The timestamp
+/// sits where it does in the snowflake, unless the [crate::FieldOrder] puts it lower
+/// than that, in which case it sits right above the guard bits instead.
+///
+/// This is the default [SequenceAllocator] used by [crate::SnowflakeGenerator].
+pub struct TimestampSequenceGenerator {
     inner: AtomicU64,
+    packing: SequencePacking,
+}
+
+impl TimestampSequenceGenerator {
+    /// Create a new TimestampSequenceGenerator starting at the given epoch-relative timestamp.
+    pub fn new(timestamp: u64, config: SnowflakeConfig) -> Self {
+        let packing = SequencePacking::new(config);
+        Self {
+            inner: AtomicU64::new(packing.initial_state(timestamp)),
+            packing,
+        }
+    }
+
+    /// Create a new TimestampSequenceGenerator that continues after the given
+    /// timestamp and sequence.
+    ///
+    /// Resuming after the last sequence ID of a timestamp leaves the overflow guard
+    /// set, so that timestamp stays used up.
+    pub(crate) fn resume(timestamp: u64, sequence: u64, config: SnowflakeConfig) -> Self {
+        let packing = SequencePacking::new(config);
+        Self {
+            inner: AtomicU64::new(packing.initial_state(timestamp) | (sequence + 1)),
+            packing,
+        }
+    }
+
+    /// Move the stored timestamp forward, resetting the sequence.
+    ///
+    /// Nothing changes if the stored timestamp is already at or past the given one.
+    pub(crate) fn advance_to(&self, timestamp: u64) {
+        self.packing.advance_to(&self.inner, timestamp);
+    }
+
+    pub(crate) fn sequence_remaining(&self, timestamp: u64) -> u64 {
+        self.packing.sequence_remaining(&self.inner, timestamp)
+    }
+
+    pub(crate) fn state(&self) -> (u64, u64) {
+        self.packing.state(&self.inner)
+    }
+
+    pub(crate) fn increment_sequence(
+        &self,
+        new_timestamp: u64,
+    ) -> Result<TimestampSequence, SnowflakeGeneratorError> {
+        self.packing.increment_sequence(&self.inner, new_timestamp)
+    }
+
+    #[cfg(any(feature = "alloc", test))]
+    pub(crate) fn reserve(
+        &self,
+        new_timestamp: u64,
+        count: u64,
+    ) -> Result<TimestampSequence, SnowflakeGeneratorError> {
+        self.packing.reserve(&self.inner, new_timestamp, count)
+    }
+}
+
+/// The CAS logic behind [TimestampSequenceGenerator], kept apart from the atomic
+/// so it can also run against state that lives outside of the generator.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct SequencePacking {
     config: SnowflakeConfig,
+    timestamp_shift: u64,
+    ordering: Ordering,
     shifted_timestamp_mask: u64,
     extended_sequence_mask: u64,
 }
 
-impl TimestampSequenceGenerator {
-    pub(crate) fn new(timestamp: u64, config: SnowflakeConfig) -> Self {
-        let shifted_timestamp = timestamp << config.timestamp_shift();
-        let extended_sequence_mask = build_mask(config.sequence_bits + 1);
-        let shifted_timestamp_mask = config.timestamp_mask << config.timestamp_shift();
-
+impl SequencePacking {
+    pub(crate) fn new(config: SnowflakeConfig) -> Self {
+        let timestamp_shift = config
+            .timestamp_shift()
+            .max(config.sequence_bits + OVERFLOW_GUARD_BITS);
         Self {
-            inner: AtomicU64::new(shifted_timestamp),
             config,
-            shifted_timestamp_mask,
-            extended_sequence_mask,
+            timestamp_shift,
+            ordering: config.atomic_ordering.ordering(),
+            shifted_timestamp_mask: config.timestamp_mask << timestamp_shift,
+            extended_sequence_mask: build_mask(config.sequence_bits + OVERFLOW_GUARD_BITS),
         }
     }
 
+    pub(crate) fn initial_state(&self, timestamp: u64) -> u64 {
+        timestamp << self.timestamp_shift
+    }
+
+    pub(crate) fn advance_to(&self, state: &AtomicU64, timestamp: u64) {
+        state.fetch_max(self.initial_state(timestamp), self.ordering);
+    }
+
+    /// The number of sequence IDs left to allocate at the given timestamp.
+    pub(crate) fn sequence_remaining(&self, state: &AtomicU64, timestamp: u64) -> u64 {
+        let state = state.load(self.ordering);
+        let per_timestamp = self.config.sequence_max + 1;
+        if timestamp > (state & self.shifted_timestamp_mask) >> self.timestamp_shift {
+            per_timestamp
+        } else {
+            per_timestamp.saturating_sub(state & self.extended_sequence_mask)
+        }
+    }
+
+    /// The stored timestamp, and the number of sequence IDs allocated at it.
+    pub(crate) fn state(&self, state: &AtomicU64) -> (u64, u64) {
+        let state = state.load(self.ordering);
+        (
+            (state & self.shifted_timestamp_mask) >> self.timestamp_shift,
+            state & self.extended_sequence_mask,
+        )
+    }
+
     pub(crate) fn increment_sequence(
         &self,
+        state: &AtomicU64,
         new_timestamp: u64,
     ) -> Result<TimestampSequence, SnowflakeGeneratorError> {
-        let mut prev_sequence = self.inner.load(Ordering::SeqCst);
-        let new_timestamp_shifted = new_timestamp << self.config.timestamp_shift();
+        let mut prev_state = state.load(self.ordering);
+        let new_timestamp_shifted = self.initial_state(new_timestamp);
 
+        // The timestamp is moved forward and the sequence taken with a single CAS. A
+        // sequence of sequence_max + 1 marks the timestamp as used up, and is never
+        // counted past, so it can't run on through the guard bits and start over.
         loop {
-            let prev_timestamp_shifted = prev_sequence & self.shifted_timestamp_mask;
-            if new_timestamp_shifted <= prev_timestamp_shifted {
-                break;
+            let prev_timestamp_shifted = prev_state & self.shifted_timestamp_mask;
+            let current_state = if new_timestamp_shifted > prev_timestamp_shifted {
+                new_timestamp_shifted
+            } else {
+                let prev_timestamp = prev_timestamp_shifted >> self.timestamp_shift;
+                self.check_clock(prev_timestamp, new_timestamp)?;
+                prev_state
+            };
+
+            let sequence = current_state & self.extended_sequence_mask;
+            if sequence > self.config.sequence_max {
+                return Err(SnowflakeGeneratorError::SequenceOverflow);
             }
 
-            match self.inner.compare_exchange(
-                prev_sequence,
-                new_timestamp_shifted,
-                Ordering::SeqCst,
-                Ordering::SeqCst,
+            match state.compare_exchange_weak(
+                prev_state,
+                current_state + 1,
+                self.ordering,
+                self.ordering,
             ) {
-                Ok(_) => break,
-                Err(updated) => prev_sequence = updated,
+                Ok(_) => {
+                    return Ok(TimestampSequence {
+                        sequence,
+                        timestamp: (current_state & self.shifted_timestamp_mask)
+                            >> self.timestamp_shift,
+                    })
+                }
+                Err(updated) => prev_state = updated,
             }
         }
+    }
 
-        let new_timestamp_sequence = self.inner.fetch_add(1, Ordering::SeqCst);
-        let masked_sequence = new_timestamp_sequence & self.extended_sequence_mask;
-        if masked_sequence > self.config.sequence_max {
-            Err(SnowflakeGeneratorError::SequenceOverflow)
-        } else {
-            let sequence = new_timestamp_sequence & self.extended_sequence_mask;
-            let timestamp = (new_timestamp_sequence & self.shifted_timestamp_mask)
-                >> self.config.timestamp_shift();
-            Ok(TimestampSequence {
-                sequence,
-                timestamp,
-            })
+    /// Reserve `count` consecutive timestamp and sequence pairs, returning the first.
+    ///
+    /// Once the sequence IDs of a timestamp are used up the run carries on at the
+    /// start of the next timestamp, so a long run reaches ahead of `new_timestamp`.
+    /// The whole run is reserved with a single CAS.
+    #[cfg(any(feature = "alloc", test))]
+    pub(crate) fn reserve(
+        &self,
+        state: &AtomicU64,
+        new_timestamp: u64,
+        count: u64,
+    ) -> Result<TimestampSequence, SnowflakeGeneratorError> {
+        let per_timestamp = self.config.sequence_max + 1;
+        let mut prev_state = state.load(self.ordering);
+
+        loop {
+            let prev_timestamp = (prev_state & self.shifted_timestamp_mask) >> self.timestamp_shift;
+            let prev_sequence = prev_state & self.extended_sequence_mask;
+            self.check_clock(prev_timestamp, new_timestamp)?;
+            let (timestamp, sequence) = if new_timestamp > prev_timestamp {
+                (new_timestamp, 0)
+            } else if prev_sequence > self.config.sequence_max {
+                (prev_timestamp + 1, 0)
+            } else {
+                (prev_timestamp, prev_sequence)
+            };
+
+            let end = sequence
+                .checked_add(count)
+                .ok_or(SnowflakeGeneratorError::TimestampOverflow)?;
+            if timestamp + (end - 1) / per_timestamp >= self.config.timestamp_max {
+                return Err(SnowflakeGeneratorError::TimestampOverflow);
+            }
+
+            // A run ending on the very last slot before timestamp_max leaves that last
+            // timestamp used up, rather than moving the state on to timestamp_max.
+            let next_timestamp = timestamp + end / per_timestamp;
+            let next_state = if next_timestamp < self.config.timestamp_max {
+                self.initial_state(next_timestamp) | (end % per_timestamp)
+            } else {
+                self.initial_state(next_timestamp - 1) | per_timestamp
+            };
+            match state.compare_exchange(prev_state, next_state, self.ordering, self.ordering) {
+                Ok(_) => {
+                    return Ok(TimestampSequence {
+                        sequence,
+                        timestamp,
+                    })
+                }
+                Err(updated) => prev_state = updated,
+            }
+        }
+    }
+
+    fn check_clock(
+        &self,
+        prev_timestamp: u64,
+        new_timestamp: u64,
+    ) -> Result<(), SnowflakeGeneratorError> {
+        match self.config.clock_backwards_policy {
+            ClockBackwardsPolicy::Reject { tolerance_ms }
+                if prev_timestamp.saturating_sub(new_timestamp) > tolerance_ms =>
+            {
+                Err(SnowflakeGeneratorError::ClockWentBackwards)
+            }
+            _ => Ok(()),
         }
     }
 }
 
-pub(crate) struct TimestampSequence {
-    sequence: u64,
-    timestamp: u64,
+impl SequenceAllocator for TimestampSequenceGenerator {
+    fn allocate(&self, timestamp: u64) -> Result<TimestampSequence, SnowflakeGeneratorError> {
+        self.increment_sequence(timestamp)
+    }
+}
+
+/// Shares one allocator between several generators, see
+/// [crate::SnowflakeGenerator::into_shared].
+#[cfg(feature = "alloc")]
+impl<S: SequenceAllocator + ?Sized> SequenceAllocator for alloc::sync::Arc<S> {
+    fn allocate(&self, timestamp: u64) -> Result<TimestampSequence, SnowflakeGeneratorError> {
+        (**self).allocate(timestamp)
+    }
+}
+
+/// Hands out sequence IDs to a [crate::SnowflakeGenerator].
+///
+/// This allows swapping out how sequence IDs are coordinated, for example with a
+/// distributed counter, while reusing the generator's timestamp handling and packing.
+/// Implementations must never return the same timestamp and sequence pair twice,
+/// and should return [SnowflakeGeneratorError::SequenceOverflow] once a timestamp's
+/// sequence IDs are used up.
+pub trait SequenceAllocator {
+    /// Allocate a sequence ID for the given epoch-relative timestamp.
+    ///
+    /// The returned timestamp may be later than the one passed in, if the allocator
+    /// has already moved on to a later timestamp.
+    fn allocate(&self, timestamp: u64) -> Result<TimestampSequence, SnowflakeGeneratorError>;
+}
+
+/// A timestamp and a sequence ID allocated for it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TimestampSequence {
+    pub sequence: u64,
+    pub timestamp: u64,
 }
 
 impl TimestampSequence {
     pub(crate) fn into_snowflake(self, machine_id: u64, config: &SnowflakeConfig) -> u64 {
-        let timestamp_bits = self.timestamp & config.timestamp_mask;
-        let machine_id_bits = machine_id & config.machine_id_mask;
-        let sequence_id_bits = self.sequence & config.sequence_mask;
-
-        timestamp_bits << config.timestamp_shift()
-            | machine_id_bits << config.sequence_bits
-            | sequence_id_bits
+        config.pack(self.timestamp, machine_id, self.sequence)
     }
 }
 
@@ -148,4 +348,138 @@ mod test {
         let snowflake = timestamp_sequence.into_snowflake(0x10, &config);
         assert_eq!(snowflake, 0x48d010000);
     }
+
+    #[test]
+    fn test_overflow_boundary() {
+        let config = SnowflakeConfig::new(42, 5, 2).unwrap();
+        let timestamp_sequence_generator = TimestampSequenceGenerator::new(0x1234, config);
+
+        for _ in 0..config.sequence_max {
+            timestamp_sequence_generator
+                .increment_sequence(0x1234)
+                .unwrap();
+        }
+        let last = timestamp_sequence_generator
+            .increment_sequence(0x1234)
+            .unwrap();
+        assert_eq!(last.sequence, config.sequence_max);
+
+        // past sequence_max + 1, repeated calls must keep failing rather than count on
+        // through the guard bit and start over
+        for _ in 0..4 * (config.sequence_max + 1) {
+            assert!(matches!(
+                timestamp_sequence_generator.increment_sequence(0x1234),
+                Err(SnowflakeGeneratorError::SequenceOverflow)
+            ));
+        }
+        assert_eq!(timestamp_sequence_generator.sequence_remaining(0x1234), 0);
+
+        let timestamp_sequence = timestamp_sequence_generator
+            .increment_sequence(0x1235)
+            .unwrap();
+        assert_eq!(timestamp_sequence.sequence, 0);
+        assert_eq!(timestamp_sequence.timestamp, 0x1235);
+    }
+
+    #[test]
+    fn test_racing_overflow() {
+        let config = SnowflakeConfig::new(42, 5, 2).unwrap();
+        let timestamp_sequence_generator = TimestampSequenceGenerator::new(0x1234, config);
+
+        let results = std::thread::scope(|scope| {
+            let handles = (0..16)
+                .map(|_| {
+                    scope.spawn(|| {
+                        (0..64)
+                            .map(|_| timestamp_sequence_generator.increment_sequence(0x1234))
+                            .collect::<Vec<_>>()
+                    })
+                })
+                .collect::<Vec<_>>();
+            handles
+                .into_iter()
+                .flat_map(|handle| handle.join().unwrap())
+                .collect::<Vec<_>>()
+        });
+
+        let mut sequences = results
+            .iter()
+            .filter_map(|result| result.as_ref().ok())
+            .map(|timestamp_sequence| timestamp_sequence.sequence)
+            .collect::<Vec<_>>();
+        sequences.sort();
+        assert_eq!(sequences, [0, 1, 2, 3]);
+        assert!(results.iter().all(|result| matches!(
+            result,
+            Ok(_) | Err(SnowflakeGeneratorError::SequenceOverflow)
+        )));
+    }
+
+    #[test]
+    fn test_full_width_overflow() {
+        // the guard bit sits in the single machine ID bit, right below the timestamp
+        let config = SnowflakeConfig::new(51, 1, 12).unwrap();
+        let timestamp = config.timestamp_max - 1;
+        let timestamp_sequence_generator = TimestampSequenceGenerator::new(timestamp, config);
+
+        for expected_sequence in 0..=config.sequence_max {
+            let timestamp_sequence = timestamp_sequence_generator
+                .increment_sequence(timestamp)
+                .unwrap();
+            assert_eq!(timestamp_sequence.sequence, expected_sequence);
+            assert_eq!(timestamp_sequence.timestamp, timestamp);
+        }
+
+        let result = timestamp_sequence_generator.increment_sequence(timestamp);
+        assert!(matches!(
+            result,
+            Err(SnowflakeGeneratorError::SequenceOverflow)
+        ));
+
+        let timestamp_sequence = timestamp_sequence_generator
+            .increment_sequence(timestamp + 1)
+            .unwrap();
+        assert_eq!(timestamp_sequence.sequence, 0);
+        assert_eq!(timestamp_sequence.timestamp, timestamp + 1);
+    }
+
+    #[test]
+    fn test_reserve_across_timestamps() {
+        let config = SnowflakeConfig::new(41, 10, 2).unwrap();
+        let timestamp_sequence_generator = TimestampSequenceGenerator::new(0x1234, config);
+        timestamp_sequence_generator
+            .increment_sequence(0x1234)
+            .unwrap();
+
+        let first = timestamp_sequence_generator.reserve(0x1234, 6).unwrap();
+        assert_eq!(first.timestamp, 0x1234);
+        assert_eq!(first.sequence, 1);
+
+        // the run took the rest of 0x1234 and the first three of 0x1235
+        let timestamp_sequence = timestamp_sequence_generator
+            .increment_sequence(0x1234)
+            .unwrap();
+        assert_eq!(timestamp_sequence.timestamp, 0x1235);
+        assert_eq!(timestamp_sequence.sequence, 3);
+    }
+
+    #[test]
+    fn test_clock_backwards_stays_monotonic() {
+        let config = SnowflakeConfig::default();
+        let timestamp_sequence_generator = TimestampSequenceGenerator::new(0x1234, config);
+
+        let mut prev = None;
+        for timestamp in [0x1234, 0x1230, 0x1000, 0x1234, 0x1233, 0x1236, 0x1235] {
+            let snowflake = timestamp_sequence_generator
+                .increment_sequence(timestamp)
+                .unwrap()
+                .into_snowflake(0x10, &config);
+            assert!(prev < Some(snowflake));
+            prev = Some(snowflake);
+        }
+
+        let last = prev.unwrap();
+        assert_eq!(config.timestamp_of(last), 0x1236);
+        assert_eq!(config.sequence_of(last), 1);
+    }
 }
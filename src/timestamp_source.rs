@@ -0,0 +1,91 @@
+//! Where generators read the current timestamp from.
+
+use crate::SnowflakeGeneratorError;
+
+/// A source of the current timestamp for a generator.
+///
+/// This is implemented for any `Fn() -> Result<u64, &'static str>`, so a plain
+/// closure or function like [crate::unix_millis] can be passed to a generator
+/// directly. Time sources with their own error types can be wrapped in a
+/// [FallibleTimestamp] instead.
+pub trait TimestampSource {
+    /// Read the current timestamp, in the unit of the generator's config.
+    fn timestamp(&self) -> Result<u64, SnowflakeGeneratorError>;
+}
+
+impl<F: Fn() -> Result<u64, &'static str>> TimestampSource for F {
+    fn timestamp(&self) -> Result<u64, SnowflakeGeneratorError> {
+        Ok(self()?)
+    }
+}
+
+/// A timestamp function whose error type converts into a [SnowflakeGeneratorError].
+///
+/// Errors are converted without loss, e.g. a [std::time::SystemTimeError] becomes a
+/// [SnowflakeGeneratorError::TimestampSourceError]. Other error types can be
+/// supported by implementing `From<MyError>` for [SnowflakeGeneratorError].
+///
+/// ```rust
+/// # #[cfg(feature = "std")] {
+/// use std::time::{SystemTime, SystemTimeError, UNIX_EPOCH};
+/// use frostbit::{FallibleTimestamp, SnowflakeGenerator};
+///
+/// let clock = FallibleTimestamp(|| -> Result<u64, SystemTimeError> {
+///     let now = SystemTime::now().duration_since(UNIX_EPOCH)?;
+///     Ok(now.as_millis() as u64)
+/// });
+/// let gen = SnowflakeGenerator::new(0, 1_288_834_974_657, clock).unwrap();
+/// assert!(gen.generate().is_ok());
+/// # }
+/// ```
+pub struct FallibleTimestamp<F>(pub F);
+
+impl<F, E> TimestampSource for FallibleTimestamp<F>
+where
+    F: Fn() -> Result<u64, E>,
+    E: Into<SnowflakeGeneratorError>,
+{
+    fn timestamp(&self) -> Result<u64, SnowflakeGeneratorError> {
+        (self.0)().map_err(Into::into)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::SnowflakeGenerator;
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_fallible_timestamp_error() {
+        #[derive(Debug)]
+        struct ClockSyncError;
+
+        impl std::fmt::Display for ClockSyncError {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                write!(f, "clock sync lost")
+            }
+        }
+
+        impl std::error::Error for ClockSyncError {}
+
+        let clock = FallibleTimestamp(|| -> Result<u64, SnowflakeGeneratorError> {
+            Err(SnowflakeGeneratorError::TimestampSourceError(Box::new(
+                ClockSyncError,
+            )))
+        });
+
+        let result = SnowflakeGenerator::new(0x10, 0, clock);
+        let Err(SnowflakeGeneratorError::TimestampSourceError(error)) = result else {
+            panic!("expected a timestamp source error");
+        };
+        assert!(error.downcast_ref::<ClockSyncError>().is_some());
+    }
+
+    #[test]
+    fn test_fallible_timestamp() {
+        let clock = FallibleTimestamp(|| Ok::<_, SnowflakeGeneratorError>(0x1234));
+        let generator = SnowflakeGenerator::new(0x10, 0, clock).unwrap();
+        assert_eq!(generator.generate().unwrap(), 0x48D010000);
+    }
+}
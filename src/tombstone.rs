@@ -0,0 +1,60 @@
+//! Helpers for snowflakes that double as deletion markers.
+
+use crate::SnowflakeConfig;
+
+/// Flip the tombstone bit of a snowflake to mark it as deleted.
+///
+/// The config must reserve a tombstone bit with
+/// [SnowflakeConfig::with_tombstone_bit], otherwise the snowflake is returned
+/// unchanged.
+pub fn mark_deleted(id: u64, config: &SnowflakeConfig) -> u64 {
+    if config.tombstone_bits > 0 {
+        id | 1 << config.tombstone_shift()
+    } else {
+        id
+    }
+}
+
+/// Check whether a snowflake has been marked as deleted with [mark_deleted].
+pub fn is_deleted(id: u64, config: &SnowflakeConfig) -> bool {
+    config.tombstone_bits > 0 && id >> config.tombstone_shift() & 1 == 1
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{SnowflakeGenerator, SnowflakeGeneratorError};
+
+    #[test]
+    fn test_mark_deleted() {
+        let config = SnowflakeConfig::default().with_tombstone_bit(true).unwrap();
+        let generator =
+            SnowflakeGenerator::new_with_config(0x10, 0, || Ok(0x1234), config).unwrap();
+
+        let snowflake = generator.generate().unwrap();
+        assert!(!is_deleted(snowflake, &config));
+
+        let deleted = mark_deleted(snowflake, &config);
+        assert_eq!(deleted, snowflake | 1 << 63);
+        assert!(is_deleted(deleted, &config));
+        assert_eq!(config.timestamp_of(deleted), 0x1234);
+    }
+
+    #[test]
+    fn test_no_tombstone_bit() {
+        let config = SnowflakeConfig::new(42, 10, 12).unwrap();
+        assert_eq!(mark_deleted(0x48D010000, &config), 0x48D010000);
+        assert!(!is_deleted(u64::MAX, &config));
+    }
+
+    #[test]
+    fn test_tombstone_bit_in_bit_sum() {
+        let config = SnowflakeConfig::new(42, 10, 12)
+            .unwrap()
+            .with_tombstone_bit(true);
+        assert!(matches!(
+            config,
+            Err(SnowflakeGeneratorError::InvalidBitConfig)
+        ));
+    }
+}
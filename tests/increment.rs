@@ -37,3 +37,115 @@ fn increment() {
         assert_eq!(timestamp, 0x400000);
     });
 }
+
+#[test]
+fn increment_relaxed() {
+    use frostbit::{AtomicOrdering, SnowflakeConfig};
+
+    let mut builder = Builder::new();
+    builder.preemption_bound = Some(3);
+    builder.check(|| {
+        let call_counter = Arc::new(AtomicU64::new(0));
+        let call_fn = move || {
+            let call_count = call_counter.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            Ok(if call_count < 2 { 0x1234 } else { 0x1235 })
+        };
+
+        let config = SnowflakeConfig::new(42, 5, 1)
+            .unwrap()
+            .with_atomic_ordering(AtomicOrdering::Relaxed);
+        let generator =
+            Arc::new(SnowflakeGenerator::new_with_config(0, 0, call_fn, config).unwrap());
+        let handles = (0..3)
+            .map(|_| {
+                let generator = generator.clone();
+                thread::spawn(move || generator.generate())
+            })
+            .collect::<Vec<_>>();
+        let mut ids = handles
+            .into_iter()
+            .filter_map(|h| h.join().unwrap().ok())
+            .collect::<Vec<_>>();
+        ids.sort();
+
+        // two snowflakes fit in a millisecond, so one thread may overflow, but
+        // whatever was generated must be unique
+        assert!(ids.len() >= 2);
+        assert!(ids.windows(2).all(|pair| pair[0] < pair[1]));
+    });
+}
+
+#[cfg(feature = "alloc")]
+#[test]
+fn batch_and_single_unique() {
+    use frostbit::SnowflakeConfig;
+
+    let mut builder = Builder::new();
+    builder.preemption_bound = Some(3);
+    builder.check(|| {
+        let call_counter = Arc::new(AtomicU64::new(0));
+        let call_fn = move || {
+            let call_count = call_counter.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            Ok(if call_count < 2 { 0x1234 } else { 0x1235 })
+        };
+
+        // four snowflakes per millisecond, so the batches spill into the next ones
+        let config = SnowflakeConfig::new(42, 5, 2).unwrap();
+        let generator =
+            Arc::new(SnowflakeGenerator::new_with_config(0, 0, call_fn, config).unwrap());
+        let batches = (0..2)
+            .map(|_| {
+                let generator = generator.clone();
+                thread::spawn(move || generator.generate_batch(3).unwrap())
+            })
+            .collect::<Vec<_>>();
+        let single = {
+            let generator = generator.clone();
+            thread::spawn(move || generator.generate().unwrap())
+        };
+
+        let mut ids = batches
+            .into_iter()
+            .flat_map(|h| h.join().unwrap())
+            .collect::<Vec<_>>();
+        ids.push(single.join().unwrap());
+        ids.sort();
+
+        assert!(ids.windows(2).all(|pair| pair[0] < pair[1]));
+        assert_eq!(ids.len(), 7);
+        for timestamp in [0x1234, 0x1235, 0x1236] {
+            assert!(ids.iter().filter(|id| *id >> 7 == timestamp).count() <= 4);
+        }
+    });
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn sharded_unique() {
+    use frostbit::{ShardedSnowflakeGenerator, SnowflakeConfig};
+
+    let mut builder = Builder::new();
+    builder.preemption_bound = Some(3);
+    builder.check(|| {
+        let config = SnowflakeConfig::new(42, 5, 2).unwrap();
+        let generator =
+            ShardedSnowflakeGenerator::new_with_config(0, 0, || Ok(0x1234), config, 1).unwrap();
+        let generator = Arc::new(generator);
+
+        // two threads share shard 1, the third has shard 0 to itself
+        let handles = [0, 1, 1]
+            .into_iter()
+            .map(|shard| {
+                let generator = generator.clone();
+                thread::spawn(move || generator.generate_on_shard(shard).unwrap())
+            })
+            .collect::<Vec<_>>();
+        let mut ids = handles
+            .into_iter()
+            .map(|h| h.join().unwrap())
+            .collect::<Vec<_>>();
+        ids.sort();
+
+        assert_eq!(ids, [0x1234 << 7, 0x1234 << 7 | 2, 0x1234 << 7 | 3]);
+    });
+}